@@ -0,0 +1,368 @@
+use crate::adapters::{
+    http::client_builder,
+    sync_backend::{
+        AnimeInfo, CurrentListStatus, SyncBackend, SyncService, SyncUpdate, WatchStatus,
+    },
+};
+
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use dialoguer::{Confirm, Password, theme::ColorfulTheme};
+use dirs_next::data_dir;
+use reqwest::Client;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value, json};
+use url::Url;
+
+const ANILIST_AUTH_URL: &str = "https://anilist.co/api/v2/oauth/authorize";
+const ANILIST_API_URL: &str = "https://graphql.anilist.co";
+/// Redirect URL to register for the API client. AniList shows the token on
+/// this page for the user to paste; it is only ever sent in the URL fragment,
+/// which a local callback server cannot see.
+pub const ANILIST_PIN_REDIRECT: &str = "https://anilist.co/api/v2/oauth/pin";
+/// AniList access tokens are valid for a year and cannot be refreshed.
+const TOKEN_LIFETIME_DAYS: i64 = 365;
+const USER_AGENT: &str = concat!("anv/", env!("CARGO_PKG_VERSION"));
+const REPEATING: &str = "REPEATING";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AniListToken {
+    pub access_token: String,
+    /// UTC timestamp when the access token expires.
+    pub expires_at: DateTime<Utc>,
+}
+
+impl AniListToken {
+    pub fn is_expired(&self) -> bool {
+        Utc::now() >= self.expires_at
+    }
+
+    pub fn token_path() -> Result<PathBuf> {
+        let base = data_dir().ok_or_else(|| anyhow!("Could not determine data directory"))?;
+        Ok(base.join("anv").join("anilist_token.json"))
+    }
+
+    pub fn load() -> Result<Option<Self>> {
+        let path = Self::token_path()?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read token file {}", path.display()))?;
+        let token: AniListToken = serde_json::from_str(&data)
+            .with_context(|| format!("failed to parse token file {}", path.display()))?;
+        Ok(Some(token))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::token_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create data directory {}", parent.display()))?;
+        }
+        let data = serde_json::to_string_pretty(self).context("failed to serialize token")?;
+        fs::write(&path, data)
+            .with_context(|| format!("failed to write token to {}", path.display()))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlError {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MediaTitle {
+    romaji: Option<String>,
+    english: Option<String>,
+    native: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Media {
+    episodes: Option<u32>,
+    media_list_entry: Option<MediaListEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MediaListEntry {
+    status: Option<String>,
+    #[serde(default)]
+    progress: u32,
+}
+
+pub struct AniListClient {
+    http: Client,
+    pub token: AniListToken,
+}
+
+impl AniListClient {
+    pub fn from_token(token: AniListToken) -> Result<Self> {
+        if token.is_expired() {
+            bail!("AniList token expired. Run `anv sync enable --service anilist` again.");
+        }
+        let http = client_builder(USER_AGENT)
+            .build()
+            .context("failed to build HTTP client")?;
+        Ok(Self { http, token })
+    }
+
+    /// Implicit-grant flow: opens the authorization page in the browser and
+    /// asks the user to paste the token AniList displays, then checks it
+    /// against the API and saves it to disk.
+    pub async fn authenticate(client_id: &str) -> Result<AniListToken> {
+        let auth_url = {
+            let mut u = Url::parse(ANILIST_AUTH_URL).expect("AniList auth URL is valid");
+            u.query_pairs_mut()
+                .append_pair("client_id", client_id)
+                .append_pair("response_type", "token");
+            u.to_string()
+        };
+
+        println!("Opening AniList authorization page in your browser...");
+        println!("If it doesn't open automatically, visit:\n  {auth_url}");
+        let _ = open::that(&auth_url);
+
+        let access_token = Password::with_theme(&ColorfulTheme::default())
+            .with_prompt("Paste the token shown by AniList")
+            .interact()?;
+        let token = AniListToken {
+            access_token: access_token.trim().to_string(),
+            expires_at: Utc::now() + chrono::Duration::days(TOKEN_LIFETIME_DAYS),
+        };
+
+        let name = Self::from_token(token.clone())?
+            .viewer_name()
+            .await
+            .context("AniList rejected the token")?;
+        println!("Signed in to AniList as {name}.");
+        token.save()?;
+        Ok(token)
+    }
+
+    async fn graphql<T: DeserializeOwned>(&self, query: &str, variables: Value) -> Result<T> {
+        let resp = self
+            .http
+            .post(ANILIST_API_URL)
+            .bearer_auth(&self.token.access_token)
+            .json(&json!({ "query": query, "variables": variables }))
+            .send()
+            .await
+            .context("AniList request failed")?;
+        let status = resp.status();
+        let body: GraphQlResponse<T> = resp
+            .json()
+            .await
+            .with_context(|| format!("failed to parse AniList response (HTTP {status})"))?;
+        if !body.errors.is_empty() {
+            let messages: Vec<String> = body.errors.into_iter().map(|e| e.message).collect();
+            bail!("AniList returned an error: {}", messages.join("; "));
+        }
+        body.data
+            .ok_or_else(|| anyhow!("AniList returned no data (HTTP {status})"))
+    }
+
+    /// The anime's episode count and the viewer's list entry for it.
+    async fn media(&self, id: u32) -> Result<Media> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct Data {
+            media: Media,
+        }
+
+        const QUERY: &str = "query ($id: Int) { Media(id: $id, type: ANIME) { \
+             episodes mediaListEntry { status progress } } }";
+        let data: Data = self.graphql(QUERY, json!({ "id": id })).await?;
+        Ok(data.media)
+    }
+
+    pub async fn viewer_name(&self) -> Result<String> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct Data {
+            viewer: Viewer,
+        }
+        #[derive(Deserialize)]
+        struct Viewer {
+            name: String,
+        }
+
+        let data: Data = self.graphql("query { Viewer { name } }", json!({})).await?;
+        Ok(data.viewer.name)
+    }
+}
+
+#[async_trait]
+impl SyncBackend for AniListClient {
+    fn service(&self) -> SyncService {
+        SyncService::Anilist
+    }
+
+    async fn resolve_anime_id(&self, title: &str) -> Result<Option<u32>> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct Data {
+            page: Page,
+        }
+        #[derive(Deserialize)]
+        struct Page {
+            media: Vec<Media>,
+        }
+        #[derive(Deserialize)]
+        struct Media {
+            id: u32,
+            title: MediaTitle,
+        }
+
+        const QUERY: &str = "query ($search: String) { \
+             Page(perPage: 5) { media(search: $search, type: ANIME) { \
+             id title { romaji english native } } } }";
+        let data: Data = self.graphql(QUERY, json!({ "search": title })).await?;
+
+        let Some(first) = data.page.media.into_iter().next() else {
+            println!("  [sync] No AniList results found for \"{title}\". Skipping sync.");
+            return Ok(None);
+        };
+
+        let en = first
+            .title
+            .english
+            .or(first.title.romaji)
+            .unwrap_or_default();
+        let display = match first.title.native.filter(|n| !n.is_empty()) {
+            Some(native) => format!("\"{en}\" ({native})"),
+            None => format!("\"{en}\""),
+        };
+
+        let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!(
+                "[sync] Found on AniList: {display}\n  Is this the correct anime?"
+            ))
+            .default(false)
+            .interact()?;
+
+        if confirmed {
+            Ok(Some(first.id))
+        } else {
+            println!("  [sync] Skipping AniList sync for this show.");
+            Ok(None)
+        }
+    }
+
+    async fn anime_info(&self, id: u32) -> Result<AnimeInfo> {
+        let media = self.media(id).await?;
+        let list_status = media.media_list_entry.and_then(|entry| {
+            let status = entry.status.as_deref().and_then(status_from_anilist)?;
+            Some(CurrentListStatus {
+                status: status.as_str().to_string(),
+                num_episodes_watched: entry.progress,
+            })
+        });
+        Ok(AnimeInfo {
+            list_status,
+            num_episodes: media.episodes.unwrap_or(0),
+        })
+    }
+
+    async fn update_anime(&self, id: u32, update: &SyncUpdate) -> Result<()> {
+        const MUTATION: &str = "mutation ($mediaId: Int, $status: MediaListStatus, \
+             $progress: Int, $scoreRaw: Int, $startedAt: FuzzyDateInput, \
+             $completedAt: FuzzyDateInput) { SaveMediaListEntry(mediaId: $mediaId, \
+             status: $status, progress: $progress, scoreRaw: $scoreRaw, \
+             startedAt: $startedAt, completedAt: $completedAt) { id } }";
+
+        // A rewatch stays a rewatch; "watching" would otherwise turn it back
+        // into a first watch.
+        let mut status = status_to_anilist(update.status);
+        if update.status == WatchStatus::Watching {
+            let entry = self.media(id).await?.media_list_entry;
+            if entry.and_then(|e| e.status).as_deref() == Some(REPEATING) {
+                status = REPEATING;
+            }
+        }
+
+        // Unset variables are left out so AniList keeps the stored values.
+        let mut variables = Map::new();
+        variables.insert("mediaId".into(), json!(id));
+        variables.insert("status".into(), json!(status));
+        variables.insert("progress".into(), json!(update.episode));
+        if let Some(score) = update.score {
+            // scoreRaw is always out of 100, whatever format the user picked.
+            variables.insert("scoreRaw".into(), json!(u32::from(score) * 10));
+        }
+        if let Some(date) = update.start_date.as_deref().and_then(fuzzy_date) {
+            variables.insert("startedAt".into(), date);
+        }
+        if let Some(date) = update.finish_date.as_deref().and_then(fuzzy_date) {
+            variables.insert("completedAt".into(), date);
+        }
+
+        let _: Value = self.graphql(MUTATION, Value::Object(variables)).await?;
+        Ok(())
+    }
+}
+
+fn status_to_anilist(status: WatchStatus) -> &'static str {
+    match status {
+        WatchStatus::Watching => "CURRENT",
+        WatchStatus::Completed => "COMPLETED",
+        WatchStatus::OnHold => "PAUSED",
+        WatchStatus::Dropped => "DROPPED",
+        WatchStatus::PlanToWatch => "PLANNING",
+    }
+}
+
+fn status_from_anilist(status: &str) -> Option<WatchStatus> {
+    match status {
+        "CURRENT" | "REPEATING" => Some(WatchStatus::Watching),
+        "COMPLETED" => Some(WatchStatus::Completed),
+        "PAUSED" => Some(WatchStatus::OnHold),
+        "DROPPED" => Some(WatchStatus::Dropped),
+        "PLANNING" => Some(WatchStatus::PlanToWatch),
+        _ => None,
+    }
+}
+
+/// `YYYY-MM-DD` as AniList's `FuzzyDateInput`.
+fn fuzzy_date(date: &str) -> Option<Value> {
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    Some(json!({ "year": date.year(), "month": date.month(), "day": date.day() }))
+}
+
+/// Build an AniListClient only when a stored, unexpired token exists.
+pub fn build_anilist_client_if_enabled() -> Option<AniListClient> {
+    match AniListToken::load() {
+        Ok(Some(token)) => match AniListClient::from_token(token) {
+            Ok(client) => Some(client),
+            Err(err) => {
+                eprintln!("[sync] Failed to initialize AniList client: {err}");
+                None
+            }
+        },
+        Ok(None) => {
+            eprintln!(
+                "[sync] Sync is enabled but no AniList token found. \
+                 Run `anv sync enable --service anilist` first."
+            );
+            None
+        }
+        Err(err) => {
+            eprintln!("[sync] Failed to load AniList token: {err}");
+            None
+        }
+    }
+}
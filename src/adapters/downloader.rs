@@ -0,0 +1,282 @@
+use crate::{
+    adapters::{
+        hls::{HlsClient, VariantPreference},
+        http::client_builder,
+        providers::USER_AGENT,
+    },
+    types::{StreamOption, Translation},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use dirs_next::{download_dir, home_dir};
+use reqwest::Client;
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+use tokio::process::Command;
+
+pub(crate) const FFMPEG_BIN: &str = "ffmpeg";
+const BYTES_PER_MIB: f64 = 1024.0 * 1024.0;
+
+/// Default root for downloaded episodes: `<downloads>/anv`, falling back to `~/anv`.
+pub fn default_download_dir() -> Result<PathBuf> {
+    download_dir()
+        .or_else(home_dir)
+        .map(|base| base.join("anv"))
+        .ok_or_else(|| anyhow!("Could not determine download directory"))
+}
+
+/// Replaces characters that are invalid in file names on common platforms
+/// while keeping the name readable (spaces and punctuation are preserved).
+pub fn sanitize_file_name(value: &str) -> String {
+    let cleaned: String = value
+        .chars()
+        .map(|ch| match ch {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            ch if ch.is_control() => '_',
+            ch => ch,
+        })
+        .collect();
+    let trimmed = cleaned.trim().trim_end_matches('.');
+    if trimmed.is_empty() {
+        String::from("unknown")
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Directory name for a show's downloads. Dubs get their own `<Title> [Dub]`
+/// directory so they never collide with (or overwrite) the subbed episodes.
+pub fn show_dir_name(title: &str, translation: Translation) -> String {
+    let title = sanitize_file_name(title);
+    match translation {
+        Translation::Sub => title,
+        other => format!("{title} [{}]", other.label()),
+    }
+}
+
+/// Path of the downloaded file for `episode` of `title` in `translation`
+/// under `base`.
+pub fn episode_file_path(
+    base: &Path,
+    title: &str,
+    translation: Translation,
+    episode: &str,
+) -> PathBuf {
+    base.join(show_dir_name(title, translation)).join(format!(
+        "{} - Episode {}.mp4",
+        sanitize_file_name(title),
+        sanitize_file_name(episode)
+    ))
+}
+
+/// Returns the already-downloaded file for `dest`, if any. HLS downloads are
+/// kept as `.ts` when ffmpeg is unavailable to remux them.
+pub fn existing_download(dest: &Path) -> Option<PathBuf> {
+    [dest.to_path_buf(), dest.with_extension("ts")]
+        .into_iter()
+        .find(|path| path.exists())
+}
+
+/// Downloads `stream` to `dest` and returns the path of the written file.
+///
+/// MP4 streams are written straight to disk. HLS streams are fetched segment
+/// by segment (picking a variant by `preference`) and remuxed into MP4 by
+/// ffmpeg when it is installed; otherwise the MPEG-TS file is kept as-is.
+/// Data is written to a `.part` file first so an interrupted download never
+/// looks complete.
+pub async fn download_stream(
+    stream: &StreamOption,
+    dest: &Path,
+    preference: VariantPreference,
+) -> Result<PathBuf> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create directory {}", parent.display()))?;
+    }
+    let partial = dest.with_extension("mp4.part");
+
+    if !stream.is_hls {
+        download_mp4(stream, &partial).await?;
+        return finish(&partial, dest);
+    }
+
+    let hls = HlsClient::new(&stream.headers)?;
+    let playlist = hls.resolve_media_playlist(&stream.url, preference).await?;
+    if playlist.is_fmp4() {
+        hls.download_segments(&playlist, &partial).await?;
+        return finish(&partial, dest);
+    }
+
+    let ts_partial = dest.with_extension("ts.part");
+    hls.download_segments(&playlist, &ts_partial).await?;
+    match remux_to_mp4(&ts_partial, &partial).await {
+        Ok(true) => {
+            let _ = fs::remove_file(&ts_partial);
+            finish(&partial, dest)
+        }
+        Ok(false) => {
+            println!("ffmpeg not found; keeping the MPEG-TS file without remuxing.");
+            finish(&ts_partial, &dest.with_extension("ts"))
+        }
+        Err(err) => {
+            let _ = fs::remove_file(&partial);
+            Err(err)
+        }
+    }
+}
+
+fn finish(partial: &Path, dest: &Path) -> Result<PathBuf> {
+    fs::rename(partial, dest)
+        .with_context(|| format!("failed to move download into {}", dest.display()))?;
+    Ok(dest.to_path_buf())
+}
+
+/// Saves the subtitle tracks of `stream` next to `dest` as
+/// `<episode>.<lang>.<ext>` and returns the paths written. Tracks that are
+/// already on disk are skipped.
+pub async fn download_subtitles(stream: &StreamOption, dest: &Path) -> Result<Vec<PathBuf>> {
+    let http = http_client()?;
+    let stem = dest
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut saved = Vec::new();
+    let mut used_names: Vec<String> = Vec::new();
+    for (idx, track) in stream.subtitles.iter().enumerate() {
+        let tag = track
+            .lang
+            .as_deref()
+            .or(track.label.as_deref())
+            .map(sanitize_file_name)
+            .unwrap_or_else(|| format!("track{}", idx + 1));
+        let ext = subtitle_extension(&track.url);
+        let mut name = format!("{stem}.{tag}.{ext}");
+        if used_names.contains(&name) {
+            name = format!("{stem}.{tag}.{}.{ext}", idx + 1);
+        }
+        used_names.push(name.clone());
+
+        let path = dest.with_file_name(&name);
+        if path.exists() {
+            continue;
+        }
+        let mut req = http.get(&track.url);
+        for (key, value) in &stream.headers {
+            req = req.header(key, value);
+        }
+        let response = req
+            .send()
+            .await
+            .with_context(|| format!("request failed for {}", track.url))?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!("HTTP {status} for subtitle {}", track.url);
+        }
+        let body = response
+            .bytes()
+            .await
+            .context("failed to read subtitle file")?;
+        fs::write(&path, &body).with_context(|| format!("failed to write {}", path.display()))?;
+        saved.push(path);
+    }
+    Ok(saved)
+}
+
+/// File extension of a subtitle URL; WebVTT when the URL does not say.
+fn subtitle_extension(url: &str) -> &'static str {
+    let path = url
+        .split(['?', '#'])
+        .next()
+        .unwrap_or(url)
+        .to_ascii_lowercase();
+    ["ass", "ssa", "srt", "vtt"]
+        .into_iter()
+        .find(|ext| path.ends_with(&format!(".{ext}")))
+        .unwrap_or("vtt")
+}
+
+fn http_client() -> Result<Client> {
+    client_builder(USER_AGENT)
+        .build()
+        .context("failed to create download HTTP client")
+}
+
+async fn download_mp4(stream: &StreamOption, partial: &Path) -> Result<()> {
+    let http = http_client()?;
+    let mut req = http.get(&stream.url);
+    for (key, value) in &stream.headers {
+        req = req.header(key, value);
+    }
+    let mut response = req
+        .send()
+        .await
+        .with_context(|| format!("request failed for {}", stream.url))?;
+    let status = response.status();
+    if !status.is_success() {
+        bail!("HTTP {status}");
+    }
+
+    let total = response.content_length();
+    let mut file = fs::File::create(partial)
+        .with_context(|| format!("failed to create {}", partial.display()))?;
+    let mut written: u64 = 0;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .context("download interrupted while reading response")?
+    {
+        file.write_all(&chunk)
+            .with_context(|| format!("failed to write {}", partial.display()))?;
+        written += chunk.len() as u64;
+        print_progress(written, total);
+    }
+    println!();
+    Ok(())
+}
+
+/// Remuxes a local MPEG-TS file into an MP4 container without re-encoding.
+/// Returns `Ok(false)` when ffmpeg is not installed.
+async fn remux_to_mp4(input: &Path, output: &Path) -> Result<bool> {
+    let status = Command::new(FFMPEG_BIN)
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-y")
+        .arg("-i")
+        .arg(input)
+        .arg("-c")
+        .arg("copy")
+        .arg("-bsf:a")
+        .arg("aac_adtstoasc")
+        .arg("-f")
+        .arg("mp4")
+        .arg(output)
+        .status()
+        .await;
+    let status = match status {
+        Ok(status) => status,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(anyhow!(err).context("failed to launch ffmpeg")),
+    };
+    if !status.success() {
+        bail!("ffmpeg exited with status {status}");
+    }
+    Ok(true)
+}
+
+fn print_progress(written: u64, total: Option<u64>) {
+    let done = written as f64 / BYTES_PER_MIB;
+    match total {
+        Some(total) if total > 0 => print!(
+            "\r  {:.1} / {:.1} MiB ({:.0}%)",
+            done,
+            total as f64 / BYTES_PER_MIB,
+            written as f64 * 100.0 / total as f64
+        ),
+        _ => print!("\r  {:.1} MiB", done),
+    }
+    let _ = std::io::stdout().flush();
+}
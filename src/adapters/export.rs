@@ -0,0 +1,387 @@
+use crate::adapters::downloader::{FFMPEG_BIN, sanitize_file_name};
+
+use anyhow::{Context, Result, anyhow, bail};
+use std::{
+    fmt::Write as _,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+use tokio::process::Command;
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+/// Metadata written to `ComicInfo.xml`, which comic readers (Komga, KOReader,
+/// Kavita, ...) use to file a CBZ under its series.
+#[derive(Debug, Clone)]
+pub struct ComicInfo<'a> {
+    pub series: &'a str,
+    pub number: &'a str,
+    pub title: &'a str,
+    pub page_count: usize,
+    pub language: Option<&'a str>,
+}
+
+impl ComicInfo<'_> {
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <ComicInfo xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" \
+             xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\">\n",
+        );
+        let _ = writeln!(xml, "  <Title>{}</Title>", escape_xml(self.title));
+        let _ = writeln!(xml, "  <Series>{}</Series>", escape_xml(self.series));
+        let _ = writeln!(xml, "  <Number>{}</Number>", escape_xml(self.number));
+        let _ = writeln!(xml, "  <PageCount>{}</PageCount>", self.page_count);
+        if let Some(language) = self.language {
+            let _ = writeln!(xml, "  <LanguageISO>{}</LanguageISO>", escape_xml(language));
+        }
+        xml.push_str("  <Manga>YesAndRightToLeft</Manga>\n</ComicInfo>\n");
+        xml
+    }
+}
+
+/// `<base>/<Title>/<Title> - Chapter <chapter>.<extension>`.
+pub fn chapter_file_path(base: &Path, title: &str, chapter: &str, extension: &str) -> PathBuf {
+    let title = sanitize_file_name(title);
+    base.join(&title).join(format!(
+        "{title} - Chapter {}.{extension}",
+        sanitize_file_name(chapter)
+    ))
+}
+
+/// Packs `pages` (in reading order) and `info` into a CBZ archive. Images are
+/// already compressed, so they are stored as-is.
+pub fn write_cbz(pages: &[PathBuf], info: &ComicInfo<'_>, dest: &Path) -> Result<()> {
+    let tmp = partial_path(dest);
+    let file = File::create(&tmp).with_context(|| format!("failed to create {}", tmp.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    zip.start_file("ComicInfo.xml", stored)?;
+    zip.write_all(info.to_xml().as_bytes())?;
+    for (idx, page) in pages.iter().enumerate() {
+        let ext = page
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("jpg");
+        let bytes =
+            fs::read(page).with_context(|| format!("failed to read page {}", page.display()))?;
+        zip.start_file(format!("{:04}.{ext}", idx + 1), stored)?;
+        zip.write_all(&bytes)?;
+    }
+    zip.finish()?;
+    fs::rename(&tmp, dest).with_context(|| format!("failed to write {}", dest.display()))
+}
+
+/// Writes `pages` as a PDF with one page per image, sized to the image.
+/// JPEGs are embedded directly; other formats are converted with ffmpeg.
+pub async fn write_pdf(pages: &[PathBuf], title: &str, dest: &Path) -> Result<()> {
+    let mut images = Vec::with_capacity(pages.len());
+    for page in pages {
+        images.push(load_jpeg(page).await?);
+    }
+
+    let mut pdf = PdfWriter::new();
+    // Objects 1-3 are the catalog, page tree and document info; each page
+    // then takes three: the page, its image and its content stream.
+    let kids: Vec<String> = (0..images.len())
+        .map(|idx| format!("{} 0 R", 4 + idx * 3))
+        .collect();
+    pdf.object(b"<< /Type /Catalog /Pages 2 0 R >>");
+    pdf.object(
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            images.len()
+        )
+        .as_bytes(),
+    );
+    pdf.object(format!("<< /Title {} /Producer (anv) >>", pdf_text(title)).as_bytes());
+
+    for (idx, image) in images.iter().enumerate() {
+        let page_id = 4 + idx * 3;
+        let (w, h) = (image.width, image.height);
+        pdf.object(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {w} {h}] \
+                 /Resources << /XObject << /Im0 {} 0 R >> >> /Contents {} 0 R >>",
+                page_id + 1,
+                page_id + 2
+            )
+            .as_bytes(),
+        );
+        let color_space = match image.components {
+            1 => "/DeviceGray",
+            4 => "/DeviceCMYK",
+            _ => "/DeviceRGB",
+        };
+        pdf.stream(
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {w} /Height {h} \
+                 /ColorSpace {color_space} /BitsPerComponent 8 /Filter /DCTDecode"
+            ),
+            &image.data,
+        );
+        pdf.stream("", format!("q {w} 0 0 {h} 0 0 cm /Im0 Do Q").as_bytes());
+    }
+
+    let tmp = partial_path(dest);
+    fs::write(&tmp, pdf.finish()).with_context(|| format!("failed to write {}", tmp.display()))?;
+    fs::rename(&tmp, dest).with_context(|| format!("failed to write {}", dest.display()))
+}
+
+struct JpegImage {
+    data: Vec<u8>,
+    width: u16,
+    height: u16,
+    components: u8,
+}
+
+async fn load_jpeg(path: &Path) -> Result<JpegImage> {
+    let data = fs::read(path).with_context(|| format!("failed to read page {}", path.display()))?;
+    let data = if jpeg_header(&data).is_some() {
+        data
+    } else {
+        convert_to_jpeg(path).await?
+    };
+    let (width, height, components) = jpeg_header(&data)
+        .ok_or_else(|| anyhow!("could not read image size of {}", path.display()))?;
+    Ok(JpegImage {
+        data,
+        width,
+        height,
+        components,
+    })
+}
+
+/// Re-encodes a PNG/WebP/AVIF page as JPEG on ffmpeg's stdout.
+async fn convert_to_jpeg(path: &Path) -> Result<Vec<u8>> {
+    let output = Command::new(FFMPEG_BIN)
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("error")
+        .arg("-i")
+        .arg(path)
+        .arg("-frames:v")
+        .arg("1")
+        .arg("-q:v")
+        .arg("2")
+        .arg("-f")
+        .arg("image2pipe")
+        .arg("-c:v")
+        .arg("mjpeg")
+        .arg("-")
+        .output()
+        .await;
+    let output = match output {
+        Ok(output) => output,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            bail!(
+                "{} is not a JPEG; install ffmpeg to convert it for PDF export",
+                path.display()
+            )
+        }
+        Err(err) => return Err(anyhow!(err).context("failed to launch ffmpeg")),
+    };
+    if !output.status.success() {
+        bail!(
+            "ffmpeg could not convert {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(output.stdout)
+}
+
+/// Width, height and colour components from a JPEG's start-of-frame marker.
+fn jpeg_header(data: &[u8]) -> Option<(u16, u16, u8)> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        match marker {
+            0xFF => {
+                pos += 1;
+                continue;
+            }
+            0x01 | 0xD0..=0xD8 => {
+                pos += 2;
+                continue;
+            }
+            _ => {}
+        }
+        let len = usize::from(u16::from_be_bytes([data[pos + 2], data[pos + 3]]));
+        let is_frame = matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+        if is_frame {
+            let segment = data.get(pos + 4..pos + 2 + len)?;
+            let height = u16::from_be_bytes([*segment.get(1)?, *segment.get(2)?]);
+            let width = u16::from_be_bytes([*segment.get(3)?, *segment.get(4)?]);
+            return Some((width, height, *segment.get(5)?));
+        }
+        pos += 2 + len;
+    }
+    None
+}
+
+/// Minimal PDF serializer: numbered objects appended in order plus the
+/// cross-reference table.
+struct PdfWriter {
+    out: Vec<u8>,
+    offsets: Vec<usize>,
+}
+
+impl PdfWriter {
+    fn new() -> Self {
+        Self {
+            out: b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec(),
+            offsets: Vec::new(),
+        }
+    }
+
+    fn object(&mut self, body: &[u8]) {
+        self.offsets.push(self.out.len());
+        let _ = writeln!(self.out, "{} 0 obj", self.offsets.len());
+        self.out.extend_from_slice(body);
+        self.out.extend_from_slice(b"\nendobj\n");
+    }
+
+    fn stream(&mut self, dict: &str, data: &[u8]) {
+        let mut body = format!("<< {dict} /Length {} >>\nstream\n", data.len()).into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\nendstream");
+        self.object(&body);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let xref = self.out.len();
+        let _ = write!(
+            self.out,
+            "xref\n0 {}\n0000000000 65535 f \n",
+            self.offsets.len() + 1
+        );
+        for offset in &self.offsets {
+            let _ = writeln!(self.out, "{offset:010} 00000 n ");
+        }
+        let _ = write!(
+            self.out,
+            "trailer\n<< /Size {} /Root 1 0 R /Info 3 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            self.offsets.len() + 1
+        );
+        self.out
+    }
+}
+
+/// A PDF text string in UTF-16BE, so titles in any script survive.
+fn pdf_text(value: &str) -> String {
+    let mut hex = String::from("<FEFF");
+    for unit in value.encode_utf16() {
+        let _ = write!(hex, "{unit:04X}");
+    }
+    hex.push('>');
+    hex
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn partial_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// SOI, an APP0 and a DHT segment (marker 0xC4 is not a frame), then a
+    /// baseline SOF0 for a 640x480, 3-component image.
+    fn jpeg(frame_marker: u8) -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8];
+        data.extend_from_slice(&[0xFF, 0xE0, 0x00, 0x10]);
+        data.extend_from_slice(b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+        data.extend_from_slice(&[0xFF, 0xC4, 0x00, 0x04, 0x00, 0x00]);
+        data.extend_from_slice(&[0xFF, frame_marker, 0x00, 0x11, 0x08]);
+        data.extend_from_slice(&[0x01, 0xE0, 0x02, 0x80, 0x03]);
+        data.extend_from_slice(&[0x01, 0x22, 0x00, 0x02, 0x11, 0x01, 0x03, 0x11, 0x01]);
+        data.extend_from_slice(&[0xFF, 0xD9]);
+        data
+    }
+
+    #[test]
+    fn reads_jpeg_dimensions_from_the_frame_header() {
+        assert_eq!(jpeg_header(&jpeg(0xC0)), Some((640, 480, 3)));
+        // Progressive JPEGs use SOF2.
+        assert_eq!(jpeg_header(&jpeg(0xC2)), Some((640, 480, 3)));
+    }
+
+    #[test]
+    fn rejects_non_jpeg_and_truncated_data() {
+        assert_eq!(jpeg_header(b"\x89PNG\r\n\x1a\n"), None);
+        let data = jpeg(0xC0);
+        assert_eq!(jpeg_header(&data[..data.len() - 12]), None);
+    }
+
+    #[test]
+    fn pdf_xref_points_at_each_object() {
+        let mut pdf = PdfWriter::new();
+        pdf.object(b"<< /Type /Catalog /Pages 2 0 R >>");
+        pdf.stream("/Type /XObject", b"\x00\x01binary\xFF");
+        pdf.object(b"<< /Title (t) >>");
+        let out = pdf.finish();
+        let text = String::from_utf8_lossy(&out);
+
+        let startxref: usize = text
+            .split("startxref\n")
+            .nth(1)
+            .and_then(|rest| rest.lines().next())
+            .and_then(|line| line.parse().ok())
+            .unwrap();
+        // The header and the stream hold binary bytes, so offsets are only
+        // meaningful in `out`; the xref section itself is plain ASCII.
+        let xref = std::str::from_utf8(&out[startxref..]).unwrap();
+        assert!(xref.starts_with("xref\n0 4\n"));
+
+        let entries: Vec<usize> = xref
+            .lines()
+            .skip(3)
+            .take(3)
+            .map(|line| line[..10].parse().unwrap())
+            .collect();
+        for (idx, offset) in entries.into_iter().enumerate() {
+            let header = format!("{} 0 obj\n", idx + 1);
+            assert!(
+                out[offset..].starts_with(header.as_bytes()),
+                "object {}",
+                idx + 1
+            );
+        }
+    }
+
+    #[test]
+    fn comic_info_escapes_metadata() {
+        let info = ComicInfo {
+            series: "Kaguya-sama: Love <is> War",
+            number: "1",
+            title: "\"Tom & Jerry's\"",
+            page_count: 20,
+            language: Some("en"),
+        };
+        let xml = info.to_xml();
+        assert!(xml.contains("<Series>Kaguya-sama: Love &lt;is&gt; War</Series>"));
+        assert!(xml.contains("<Title>&quot;Tom &amp; Jerry&apos;s&quot;</Title>"));
+        assert!(xml.contains("<PageCount>20</PageCount>"));
+        assert!(xml.contains("<LanguageISO>en</LanguageISO>"));
+    }
+}
@@ -0,0 +1,634 @@
+use crate::{
+    adapters::{http::client_builder, providers::USER_AGENT},
+    types::StreamOption,
+};
+
+use aes::Aes128;
+use anyhow::{Context, Result, anyhow, bail};
+use cbc::cipher::{BlockDecryptMut, KeyIvInit, block_padding::Pkcs7};
+use futures::{
+    future::join_all,
+    stream::{self, StreamExt},
+};
+use reqwest::Client;
+use std::{collections::HashMap, fs, io::Write, path::Path, time::Duration};
+use url::Url;
+
+const SEGMENT_CONCURRENCY: usize = 8;
+const SEGMENT_RETRIES: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// A parsed `.m3u8` document: either a master playlist listing variants or a
+/// media playlist listing segments.
+#[derive(Debug, Clone)]
+pub enum Playlist {
+    Master(MasterPlaylist),
+    Media(MediaPlaylist),
+}
+
+#[derive(Debug, Clone)]
+pub struct MasterPlaylist {
+    pub variants: Vec<Variant>,
+}
+
+/// One `#EXT-X-STREAM-INF` entry of a master playlist.
+#[derive(Debug, Clone)]
+pub struct Variant {
+    /// Absolute URL of the variant's media playlist.
+    pub url: String,
+    pub bandwidth: u64,
+    /// `(width, height)` when the playlist advertises a `RESOLUTION`.
+    pub resolution: Option<(u32, u32)>,
+}
+
+impl Variant {
+    pub fn height(&self) -> Option<u32> {
+        self.resolution.map(|(_, h)| h)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MediaPlaylist {
+    pub segments: Vec<Segment>,
+    /// `#EXT-X-MAP` initialization section for fragmented-MP4 playlists.
+    pub init_section: Option<String>,
+}
+
+impl MediaPlaylist {
+    /// Fragmented-MP4 playlists concatenate into an MP4; everything else is MPEG-TS.
+    pub fn is_fmp4(&self) -> bool {
+        self.init_section.is_some()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub url: String,
+    pub duration: f64,
+    pub sequence: u64,
+    pub key: Option<SegmentKey>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentKey {
+    pub url: String,
+    /// Explicit IV; when absent the segment's media sequence number is used.
+    pub iv: Option<[u8; 16]>,
+}
+
+/// How to choose a variant from a master playlist.
+#[derive(Debug, Clone, Copy)]
+pub enum VariantPreference {
+    /// Highest bandwidth available.
+    Best,
+    /// Exact height (e.g. 720), otherwise the tallest variant below it,
+    /// otherwise the smallest available.
+    Height(u32),
+    /// Highest bandwidth not exceeding the given bits per second.
+    MaxBandwidth(u64),
+}
+
+impl MasterPlaylist {
+    pub fn select(&self, preference: VariantPreference) -> Option<&Variant> {
+        let best = || self.variants.iter().max_by_key(|v| v.bandwidth);
+        match preference {
+            VariantPreference::Best => best(),
+            VariantPreference::Height(target) => self
+                .variants
+                .iter()
+                .filter(|v| v.height() == Some(target))
+                .max_by_key(|v| v.bandwidth)
+                .or_else(|| {
+                    self.variants
+                        .iter()
+                        .filter(|v| v.height().is_some_and(|h| h < target))
+                        .max_by_key(|v| (v.height(), v.bandwidth))
+                })
+                .or_else(|| self.variants.iter().min_by_key(|v| v.bandwidth)),
+            VariantPreference::MaxBandwidth(limit) => self
+                .variants
+                .iter()
+                .filter(|v| v.bandwidth <= limit)
+                .max_by_key(|v| v.bandwidth)
+                .or_else(|| self.variants.iter().min_by_key(|v| v.bandwidth)),
+        }
+    }
+}
+
+/// Adds one option per variant (e.g. 1080p, 720p, 480p), ranked by its real
+/// resolution, after every HLS option that points at a master playlist. The
+/// master option itself is kept with its own rank so "auto" (adaptive) still
+/// wins when it did before.
+///
+/// Options whose playlist cannot be fetched, or that already point at a media
+/// playlist, are kept unchanged.
+pub async fn expand_master_playlists(options: Vec<StreamOption>) -> Vec<StreamOption> {
+    let expanded = join_all(options.into_iter().map(|option| async move {
+        if !option.is_hls {
+            return vec![option];
+        }
+        let variants = expand_option(&option).await.unwrap_or_default();
+        std::iter::once(option).chain(variants).collect()
+    }))
+    .await;
+    expanded.into_iter().flatten().collect()
+}
+
+async fn expand_option(option: &StreamOption) -> Result<Vec<StreamOption>> {
+    let client = HlsClient::new(&option.headers)?;
+    let Playlist::Master(master) = client.fetch_playlist(&option.url).await? else {
+        return Ok(Vec::new());
+    };
+
+    let mut variants = master.variants;
+    variants.sort_by_key(|v| std::cmp::Reverse((v.height(), v.bandwidth)));
+    // Several variants can share a resolution (different bitrates); keep the
+    // best one. Variants without a resolution are told apart by bitrate only.
+    variants.dedup_by(|v, kept| v.height().is_some() && v.height() == kept.height());
+
+    Ok(variants
+        .into_iter()
+        .map(|variant| {
+            let (quality_label, quality_rank) = match variant.height() {
+                Some(height) => (format!("{height}p"), height as i32),
+                None => (format!("{}kbps", variant.bandwidth / 1000), 0),
+            };
+            StreamOption {
+                url: variant.url,
+                quality_label,
+                quality_rank,
+                ..option.clone()
+            }
+        })
+        .collect())
+}
+
+/// Parses an `.m3u8` document. Relative URIs are resolved against `base_url`.
+pub fn parse_playlist(text: &str, base_url: &str) -> Result<Playlist> {
+    let base = Url::parse(base_url).with_context(|| format!("invalid playlist URL {base_url}"))?;
+    let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+    if lines.next() != Some("#EXTM3U") {
+        bail!("not an HLS playlist (missing #EXTM3U header)");
+    }
+
+    let mut variants = Vec::new();
+    let mut segments = Vec::new();
+    let mut init_section = None;
+    let mut pending_variant: Option<(u64, Option<(u32, u32)>)> = None;
+    let mut pending_duration: Option<f64> = None;
+    let mut current_key: Option<SegmentKey> = None;
+    let mut sequence: u64 = 0;
+
+    for line in lines {
+        if let Some(attrs) = line.strip_prefix("#EXT-X-STREAM-INF:") {
+            let attrs = parse_attributes(attrs);
+            let bandwidth = attrs
+                .get("BANDWIDTH")
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(0);
+            let resolution = attrs.get("RESOLUTION").and_then(|v| {
+                let (w, h) = v.split_once('x')?;
+                Some((w.parse().ok()?, h.parse().ok()?))
+            });
+            pending_variant = Some((bandwidth, resolution));
+        } else if let Some(value) = line.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+            sequence = value.parse().unwrap_or(0);
+        } else if let Some(value) = line.strip_prefix("#EXTINF:") {
+            let duration = value.split(',').next().unwrap_or_default();
+            pending_duration = Some(duration.parse().unwrap_or(0.0));
+        } else if let Some(attrs) = line.strip_prefix("#EXT-X-KEY:") {
+            let attrs = parse_attributes(attrs);
+            current_key = match attrs.get("METHOD").map(String::as_str) {
+                Some("NONE") | None => None,
+                Some("AES-128") => {
+                    let uri = attrs
+                        .get("URI")
+                        .ok_or_else(|| anyhow!("AES-128 key without URI"))?;
+                    let iv = attrs.get("IV").map(|v| parse_iv(v)).transpose()?;
+                    Some(SegmentKey {
+                        url: resolve(&base, uri)?,
+                        iv,
+                    })
+                }
+                Some(other) => bail!("unsupported HLS encryption method {other}"),
+            };
+        } else if let Some(attrs) = line.strip_prefix("#EXT-X-MAP:") {
+            let attrs = parse_attributes(attrs);
+            if let Some(uri) = attrs.get("URI") {
+                init_section = Some(resolve(&base, uri)?);
+            }
+        } else if line.starts_with('#') {
+            continue;
+        } else if let Some((bandwidth, resolution)) = pending_variant.take() {
+            variants.push(Variant {
+                url: resolve(&base, line)?,
+                bandwidth,
+                resolution,
+            });
+        } else if let Some(duration) = pending_duration.take() {
+            segments.push(Segment {
+                url: resolve(&base, line)?,
+                duration,
+                sequence,
+                key: current_key.clone(),
+            });
+            sequence += 1;
+        }
+    }
+
+    if !variants.is_empty() {
+        Ok(Playlist::Master(MasterPlaylist { variants }))
+    } else if !segments.is_empty() {
+        Ok(Playlist::Media(MediaPlaylist {
+            segments,
+            init_section,
+        }))
+    } else {
+        bail!("HLS playlist contains no variants or segments")
+    }
+}
+
+/// Parses an attribute list such as `BANDWIDTH=800000,CODECS="avc1,mp4a"`.
+fn parse_attributes(input: &str) -> HashMap<String, String> {
+    let mut attrs = HashMap::new();
+    let mut rest = input;
+    while !rest.is_empty() {
+        let Some((key, after_eq)) = rest.split_once('=') else {
+            break;
+        };
+        let (value, remaining) = if let Some(quoted) = after_eq.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            let remaining = quoted[end..].trim_start_matches('"');
+            (&quoted[..end], remaining)
+        } else {
+            let end = after_eq.find(',').unwrap_or(after_eq.len());
+            (&after_eq[..end], &after_eq[end..])
+        };
+        attrs.insert(key.trim().to_ascii_uppercase(), value.to_string());
+        rest = remaining.trim_start_matches(',').trim_start();
+    }
+    attrs
+}
+
+fn parse_iv(value: &str) -> Result<[u8; 16]> {
+    let hex = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+    if hex.len() != 32 {
+        bail!("invalid HLS IV '{value}'");
+    }
+    let mut iv = [0u8; 16];
+    for (idx, chunk) in hex.as_bytes().chunks(2).enumerate() {
+        let pair = std::str::from_utf8(chunk).map_err(|_| anyhow!("invalid HLS IV '{value}'"))?;
+        iv[idx] = u8::from_str_radix(pair, 16).map_err(|_| anyhow!("invalid HLS IV '{value}'"))?;
+    }
+    Ok(iv)
+}
+
+fn resolve(base: &Url, uri: &str) -> Result<String> {
+    base.join(uri)
+        .map(|u| u.to_string())
+        .with_context(|| format!("invalid URI '{uri}' in HLS playlist"))
+}
+
+/// HTTP client for playlists, keys and segments that carries the stream's
+/// request headers (Referer, Origin, ...) on every request.
+pub struct HlsClient {
+    http: Client,
+    headers: HashMap<String, String>,
+}
+
+impl HlsClient {
+    pub fn new(headers: &HashMap<String, String>) -> Result<Self> {
+        let http = client_builder(USER_AGENT)
+            .build()
+            .context("failed to create HLS HTTP client")?;
+        Ok(Self {
+            http,
+            headers: headers.clone(),
+        })
+    }
+
+    pub async fn fetch_playlist(&self, url: &str) -> Result<Playlist> {
+        let bytes = self.fetch_bytes(url).await?;
+        let text = String::from_utf8_lossy(&bytes);
+        parse_playlist(&text, url)
+    }
+
+    /// Fetches `url` and follows a master playlist to the preferred variant's
+    /// media playlist.
+    pub async fn resolve_media_playlist(
+        &self,
+        url: &str,
+        preference: VariantPreference,
+    ) -> Result<MediaPlaylist> {
+        match self.fetch_playlist(url).await? {
+            Playlist::Media(media) => Ok(media),
+            Playlist::Master(master) => {
+                let variant = master
+                    .select(preference)
+                    .ok_or_else(|| anyhow!("HLS master playlist has no variants"))?;
+                match self.fetch_playlist(&variant.url).await? {
+                    Playlist::Media(media) => Ok(media),
+                    Playlist::Master(_) => bail!("nested HLS master playlists are not supported"),
+                }
+            }
+        }
+    }
+
+    /// Downloads every segment of `playlist` into `dest`, in order.
+    ///
+    /// Segments are fetched concurrently and retried with exponential backoff;
+    /// AES-128 encrypted segments are decrypted before being written.
+    pub async fn download_segments(&self, playlist: &MediaPlaylist, dest: &Path) -> Result<()> {
+        let mut keys: HashMap<String, [u8; 16]> = HashMap::new();
+        for key in playlist.segments.iter().filter_map(|s| s.key.as_ref()) {
+            if keys.contains_key(&key.url) {
+                continue;
+            }
+            let bytes = self
+                .fetch_with_retries(&key.url)
+                .await
+                .context("failed to fetch HLS decryption key")?;
+            let key_bytes: [u8; 16] = bytes
+                .as_slice()
+                .try_into()
+                .map_err(|_| anyhow!("HLS key has {} bytes, expected 16", bytes.len()))?;
+            keys.insert(key.url.clone(), key_bytes);
+        }
+
+        let mut file = fs::File::create(dest)
+            .with_context(|| format!("failed to create {}", dest.display()))?;
+        if let Some(init) = &playlist.init_section {
+            let bytes = self
+                .fetch_with_retries(init)
+                .await
+                .context("failed to fetch HLS init section")?;
+            file.write_all(&bytes)
+                .with_context(|| format!("failed to write {}", dest.display()))?;
+        }
+
+        let total = playlist.segments.len();
+        let keys = &keys;
+        let mut segments = stream::iter(playlist.segments.iter())
+            .map(|segment| async move {
+                let data = self
+                    .fetch_with_retries(&segment.url)
+                    .await
+                    .with_context(|| format!("failed to fetch segment {}", segment.sequence))?;
+                match &segment.key {
+                    Some(key) => decrypt_segment(segment, key, keys, data),
+                    None => Ok(data),
+                }
+            })
+            .buffered(SEGMENT_CONCURRENCY);
+
+        let mut done = 0usize;
+        while let Some(data) = segments.next().await {
+            let data = data?;
+            file.write_all(&data)
+                .with_context(|| format!("failed to write {}", dest.display()))?;
+            done += 1;
+            print!("\r  segment {done}/{total}");
+            let _ = std::io::stdout().flush();
+        }
+        println!();
+        Ok(())
+    }
+
+    async fn fetch_with_retries(&self, url: &str) -> Result<Vec<u8>> {
+        let mut attempt = 0;
+        loop {
+            match self.fetch_bytes(url).await {
+                Ok(bytes) => return Ok(bytes),
+                Err(err) if attempt + 1 >= SEGMENT_RETRIES => return Err(err),
+                Err(_) => {
+                    tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(attempt)).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    async fn fetch_bytes(&self, url: &str) -> Result<Vec<u8>> {
+        let mut req = self.http.get(url);
+        for (key, value) in &self.headers {
+            req = req.header(key, value);
+        }
+        let resp = req
+            .send()
+            .await
+            .with_context(|| format!("request failed for {url}"))?;
+        let status = resp.status();
+        if !status.is_success() {
+            bail!("HTTP {status} for {url}");
+        }
+        resp.bytes()
+            .await
+            .map(|b| b.to_vec())
+            .with_context(|| format!("failed to read bytes for {url}"))
+    }
+}
+
+fn decrypt_segment(
+    segment: &Segment,
+    key: &SegmentKey,
+    keys: &HashMap<String, [u8; 16]>,
+    data: Vec<u8>,
+) -> Result<Vec<u8>> {
+    let key_bytes = keys
+        .get(&key.url)
+        .ok_or_else(|| anyhow!("missing HLS key {}", key.url))?;
+    let iv = key.iv.unwrap_or_else(|| {
+        let mut iv = [0u8; 16];
+        iv[8..].copy_from_slice(&segment.sequence.to_be_bytes());
+        iv
+    });
+    cbc::Decryptor::<Aes128>::new(key_bytes.into(), &iv.into())
+        .decrypt_padded_vec_mut::<Pkcs7>(&data)
+        .map_err(|_| anyhow!("failed to decrypt segment {}", segment.sequence))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "https://cdn.example.com/show/ep1/master.m3u8";
+    /// AES-128 key and IV from NIST SP 800-38A, F.2.1.
+    const KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const IV: &str = "000102030405060708090a0b0c0d0e0f";
+
+    fn hex(value: &str) -> Vec<u8> {
+        (0..value.len())
+            .step_by(2)
+            .map(|idx| u8::from_str_radix(&value[idx..idx + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn media(text: &str) -> MediaPlaylist {
+        match parse_playlist(text, BASE).unwrap() {
+            Playlist::Media(media) => media,
+            Playlist::Master(_) => panic!("expected a media playlist"),
+        }
+    }
+
+    #[test]
+    fn parses_master_playlist() {
+        let text = "#EXTM3U\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=5000000,RESOLUTION=1920x1080,CODECS=\"avc1.640028,mp4a.40.2\"\n\
+            1080/index.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=2800000,RESOLUTION=1280x720\n\
+            https://other.example.com/720.m3u8\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=64000\n\
+            /audio/index.m3u8\n";
+        let Playlist::Master(master) = parse_playlist(text, BASE).unwrap() else {
+            panic!("expected a master playlist");
+        };
+        assert_eq!(master.variants.len(), 3);
+        assert_eq!(
+            master.variants[0].url,
+            "https://cdn.example.com/show/ep1/1080/index.m3u8"
+        );
+        assert_eq!(master.variants[0].bandwidth, 5_000_000);
+        assert_eq!(master.variants[0].resolution, Some((1920, 1080)));
+        assert_eq!(master.variants[1].url, "https://other.example.com/720.m3u8");
+        assert_eq!(master.variants[2].height(), None);
+
+        assert_eq!(
+            master.select(VariantPreference::Best).unwrap().bandwidth,
+            5_000_000
+        );
+        assert_eq!(
+            master
+                .select(VariantPreference::Height(900))
+                .unwrap()
+                .height(),
+            Some(720)
+        );
+        assert_eq!(
+            master
+                .select(VariantPreference::MaxBandwidth(3_000_000))
+                .unwrap()
+                .bandwidth,
+            2_800_000
+        );
+    }
+
+    #[test]
+    fn parses_media_playlist() {
+        let playlist = media(
+            "#EXTM3U\n\
+             #EXT-X-VERSION:3\n\
+             #EXT-X-TARGETDURATION:10\n\
+             #EXT-X-MEDIA-SEQUENCE:7\n\
+             #EXTINF:9.009,\n\
+             seg-7.ts\n\
+             #EXTINF:4.5,title\n\
+             ../shared/seg-8.ts\n\
+             #EXT-X-ENDLIST\n",
+        );
+        assert!(!playlist.is_fmp4());
+        assert_eq!(playlist.segments.len(), 2);
+        assert_eq!(
+            playlist.segments[0].url,
+            "https://cdn.example.com/show/ep1/seg-7.ts"
+        );
+        assert_eq!(playlist.segments[0].duration, 9.009);
+        assert_eq!(playlist.segments[0].sequence, 7);
+        assert_eq!(
+            playlist.segments[1].url,
+            "https://cdn.example.com/show/shared/seg-8.ts"
+        );
+        assert_eq!(playlist.segments[1].sequence, 8);
+        assert!(playlist.segments.iter().all(|s| s.key.is_none()));
+    }
+
+    #[test]
+    fn parses_fmp4_init_section() {
+        let playlist = media(
+            "#EXTM3U\n\
+             #EXT-X-MAP:URI=\"init.mp4\"\n\
+             #EXTINF:6.0,\n\
+             seg-0.m4s\n",
+        );
+        assert!(playlist.is_fmp4());
+        assert_eq!(
+            playlist.init_section.as_deref(),
+            Some("https://cdn.example.com/show/ep1/init.mp4")
+        );
+    }
+
+    #[test]
+    fn applies_keys_until_changed() {
+        let playlist = media(&format!(
+            "#EXTM3U\n\
+             #EXTINF:4.0,\n\
+             clear.ts\n\
+             #EXT-X-KEY:METHOD=AES-128,URI=\"/keys/k1.bin\",IV=0x{IV}\n\
+             #EXTINF:4.0,\n\
+             enc-1.ts\n\
+             #EXT-X-KEY:METHOD=AES-128,URI=\"k2.bin\"\n\
+             #EXTINF:4.0,\n\
+             enc-2.ts\n\
+             #EXT-X-KEY:METHOD=NONE\n\
+             #EXTINF:4.0,\n\
+             clear-again.ts\n"
+        ));
+        let keys: Vec<Option<&SegmentKey>> =
+            playlist.segments.iter().map(|s| s.key.as_ref()).collect();
+        assert_eq!(keys[0], None);
+        let first = keys[1].unwrap();
+        assert_eq!(first.url, "https://cdn.example.com/keys/k1.bin");
+        assert_eq!(first.iv.map(|iv| iv.to_vec()), Some(hex(IV)));
+        let second = keys[2].unwrap();
+        assert_eq!(second.url, "https://cdn.example.com/show/ep1/k2.bin");
+        assert_eq!(second.iv, None);
+        assert_eq!(keys[3], None);
+    }
+
+    #[test]
+    fn rejects_invalid_playlists() {
+        assert!(parse_playlist("#EXTINF:4.0,\nseg.ts\n", BASE).is_err());
+        assert!(parse_playlist("#EXTM3U\n#EXT-X-ENDLIST\n", BASE).is_err());
+        let sample_aes = "#EXTM3U\n\
+            #EXT-X-KEY:METHOD=SAMPLE-AES,URI=\"k.bin\"\n\
+            #EXTINF:4.0,\n\
+            seg.ts\n";
+        assert!(parse_playlist(sample_aes, BASE).is_err());
+        assert!(parse_iv("0x1234").is_err());
+    }
+
+    fn decrypt(iv: Option<[u8; 16]>, sequence: u64, data: Vec<u8>) -> Result<Vec<u8>> {
+        let key = SegmentKey {
+            url: String::from("https://cdn.example.com/key.bin"),
+            iv,
+        };
+        let segment = Segment {
+            url: String::from("https://cdn.example.com/seg.ts"),
+            duration: 4.0,
+            sequence,
+            key: Some(key.clone()),
+        };
+        let keys = HashMap::from([(key.url.clone(), hex(KEY).try_into().unwrap())]);
+        decrypt_segment(&segment, &key, &keys, data)
+    }
+
+    #[test]
+    fn decrypts_aes128_with_explicit_iv() {
+        // NIST SP 800-38A F.2.1 block 1, followed by a full PKCS#7 padding block.
+        let ciphertext = hex("7649abac8119b246cee98e9b12e9197d8964e0b149c10b7b682e6e39aaeb731c");
+        let iv = hex(IV).try_into().unwrap();
+        let plain = decrypt(Some(iv), 0, ciphertext).unwrap();
+        assert_eq!(plain, hex("6bc1bee22e409f96e93d7e117393172a"));
+    }
+
+    #[test]
+    fn decrypts_aes128_with_sequence_iv() {
+        // Without an IV attribute the media sequence number (42) is the IV.
+        let ciphertext = hex("4f4f64bf21d29cde3b4a319ae5e657f5");
+        let plain = decrypt(None, 42, ciphertext.clone()).unwrap();
+        assert_eq!(plain, b"anv segment");
+        assert!(decrypt(None, 43, ciphertext).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use reqwest::{Client, ClientBuilder, IntoUrl, RequestBuilder, Response};
+
+use crate::adapters::providers::{ProviderError, ProviderResult, error::ensure_success};
+
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest silence tolerated while waiting for response bytes.
+pub const READ_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_ATTEMPTS: u32 = 4;
+const BACKOFF_BASE: Duration = Duration::from_millis(500);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+const BACKOFF_JITTER_MS: u64 = 250;
+
+/// Earliest time the next request to each host may start, shared by every
+/// client so concurrent searches respect the same limit.
+static NEXT_SLOT: LazyLock<Mutex<HashMap<String, Instant>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// A `reqwest` builder with anv's user agent and connect/read timeouts.
+pub fn client_builder(user_agent: &str) -> ClientBuilder {
+    Client::builder()
+        .user_agent(user_agent)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .read_timeout(READ_TIMEOUT)
+}
+
+/// HTTP client shared by the providers: retries network errors, 429s and 5xx
+/// responses with exponential backoff (honouring `Retry-After`) and spaces
+/// requests to the same host.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: Client,
+    min_interval: Duration,
+    max_attempts: u32,
+}
+
+impl HttpClient {
+    pub fn new(user_agent: &str) -> Result<Self> {
+        Ok(Self::from_client(client_builder(user_agent).build()?))
+    }
+
+    /// Wraps a client built with [`client_builder`] plus extra settings
+    /// (default headers, redirect policy, ...).
+    pub fn from_client(client: Client) -> Self {
+        Self {
+            client,
+            min_interval: Duration::ZERO,
+            max_attempts: MAX_ATTEMPTS,
+        }
+    }
+
+    /// Starts requests to the same host at least `interval` apart.
+    pub fn with_rate_limit(mut self, interval: Duration) -> Self {
+        self.min_interval = interval;
+        self
+    }
+
+    pub fn get(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    /// Sends `request` and returns the first successful response. Failures that
+    /// may clear up on their own are retried; everything else, and the last
+    /// failure, is returned as a [`ProviderError`].
+    pub async fn send(&self, request: RequestBuilder) -> ProviderResult<Response> {
+        let mut request = request;
+        let mut attempt = 1;
+        loop {
+            let retry = if attempt < self.max_attempts {
+                request.try_clone()
+            } else {
+                None
+            };
+            let built = request.build()?;
+            if let Some(host) = built.url().host_str() {
+                self.wait_for_slot(host).await;
+            }
+            let err = match self.client.execute(built).await {
+                // Only seen with a non-following redirect policy; the caller
+                // handles the redirect itself.
+                Ok(response) if response.status().is_redirection() => return Ok(response),
+                Ok(response) => match ensure_success(response) {
+                    Ok(response) => return Ok(response),
+                    Err(err) => err,
+                },
+                Err(err) => err.into(),
+            };
+            let Some(next) = retry.filter(|_| is_transient(&err)) else {
+                return Err(err);
+            };
+            tokio::time::sleep(backoff(attempt, &err)).await;
+            request = next;
+            attempt += 1;
+        }
+    }
+
+    async fn wait_for_slot(&self, host: &str) {
+        if self.min_interval.is_zero() {
+            return;
+        }
+        let wait = {
+            let mut slots = NEXT_SLOT.lock().expect("rate limit table poisoned");
+            let now = Instant::now();
+            let slot = slots.get(host).copied().unwrap_or(now).max(now);
+            slots.insert(host.to_string(), slot + self.min_interval);
+            slot - now
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+fn is_transient(err: &ProviderError) -> bool {
+    match err {
+        ProviderError::Network(_) | ProviderError::RateLimited { .. } => true,
+        ProviderError::Http(status) => status.is_server_error(),
+        _ => false,
+    }
+}
+
+fn backoff(attempt: u32, err: &ProviderError) -> Duration {
+    if let ProviderError::RateLimited {
+        retry_after: Some(wait),
+    } = err
+    {
+        return (*wait).min(BACKOFF_MAX);
+    }
+    let exponential = BACKOFF_BASE.saturating_mul(2u32.saturating_pow(attempt - 1));
+    let jitter = Duration::from_millis(rand::random_range(0..BACKOFF_JITTER_MS));
+    exponential.min(BACKOFF_MAX) + jitter
+}
@@ -0,0 +1,416 @@
+use crate::adapters::{
+    http::client_builder,
+    sync_backend::{
+        AnimeInfo, CurrentListStatus, SyncBackend, SyncService, SyncUpdate, WatchStatus,
+    },
+};
+
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dialoguer::{Confirm, Input, Password, theme::ColorfulTheme};
+use dirs_next::data_dir;
+use reqwest::{Client, RequestBuilder, header::CONTENT_TYPE};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value, json};
+
+const KITSU_TOKEN_URL: &str = "https://kitsu.app/api/oauth/token";
+const KITSU_API_BASE: &str = "https://kitsu.app/api/edge";
+const JSON_API: &str = "application/vnd.api+json";
+const USER_AGENT: &str = concat!("anv/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KitsuToken {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// UTC timestamp when the access token expires.
+    pub expires_at: DateTime<Utc>,
+    /// Kitsu user the token belongs to; library entries are filed under it.
+    pub user_id: String,
+}
+
+impl KitsuToken {
+    pub fn is_expired(&self) -> bool {
+        // Treat as expired 60 s before actual expiry so we refresh proactively.
+        Utc::now() >= self.expires_at - chrono::Duration::seconds(60)
+    }
+
+    pub fn token_path() -> Result<PathBuf> {
+        let base = data_dir().ok_or_else(|| anyhow!("Could not determine data directory"))?;
+        Ok(base.join("anv").join("kitsu_token.json"))
+    }
+
+    pub fn load() -> Result<Option<Self>> {
+        let path = Self::token_path()?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let data = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read token file {}", path.display()))?;
+        let token: KitsuToken = serde_json::from_str(&data)
+            .with_context(|| format!("failed to parse token file {}", path.display()))?;
+        Ok(Some(token))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::token_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create data directory {}", parent.display()))?;
+        }
+        let data = serde_json::to_string_pretty(self).context("failed to serialize token")?;
+        fs::write(&path, data)
+            .with_context(|| format!("failed to write token to {}", path.display()))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: String,
+    expires_in: i64,
+}
+
+/// A JSON:API document whose `data` is a list of resources.
+#[derive(Debug, Deserialize)]
+struct Collection<T> {
+    data: Vec<Resource<T>>,
+}
+
+/// A JSON:API document holding a single resource.
+#[derive(Debug, Deserialize)]
+struct Single<T> {
+    data: Resource<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Resource<T> {
+    id: String,
+    attributes: T,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AnimeAttributes {
+    #[serde(default)]
+    canonical_title: String,
+    #[serde(default)]
+    titles: AnimeTitles,
+    episode_count: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AnimeTitles {
+    en: Option<String>,
+    ja_jp: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LibraryEntryAttributes {
+    status: String,
+    #[serde(default)]
+    progress: u32,
+}
+
+pub struct KitsuClient {
+    http: Client,
+    pub token: KitsuToken,
+}
+
+impl KitsuClient {
+    /// Build a `KitsuClient` from an existing (possibly expired) token.
+    pub async fn from_token(mut token: KitsuToken) -> Result<Self> {
+        let http = client_builder(USER_AGENT)
+            .build()
+            .context("failed to build HTTP client")?;
+
+        if token.is_expired() {
+            token = Self::request_token(
+                &http,
+                &[
+                    ("grant_type", "refresh_token"),
+                    ("refresh_token", &token.refresh_token),
+                ],
+                token.user_id.clone(),
+            )
+            .await?;
+            token.save()?;
+        }
+
+        Ok(Self { http, token })
+    }
+
+    /// Password grant: asks for the Kitsu email and password, exchanges them
+    /// for tokens and saves those to disk. The password itself is not stored.
+    pub async fn authenticate() -> Result<KitsuToken> {
+        let theme = ColorfulTheme::default();
+        let email: String = Input::with_theme(&theme)
+            .with_prompt("Kitsu email")
+            .interact_text()?;
+        let password = Password::with_theme(&theme)
+            .with_prompt("Kitsu password")
+            .interact()?;
+
+        let http = client_builder(USER_AGENT)
+            .build()
+            .context("failed to build HTTP client")?;
+        let mut token = Self::request_token(
+            &http,
+            &[
+                ("grant_type", "password"),
+                ("username", email.trim()),
+                ("password", &password),
+            ],
+            String::new(),
+        )
+        .await?;
+
+        let client = Self {
+            http,
+            token: token.clone(),
+        };
+        token.user_id = client.current_user_id().await?;
+        token.save()?;
+        Ok(token)
+    }
+
+    async fn request_token(
+        http: &Client,
+        params: &[(&str, &str)],
+        user_id: String,
+    ) -> Result<KitsuToken> {
+        let resp: TokenResponse = http
+            .post(KITSU_TOKEN_URL)
+            .form(params)
+            .send()
+            .await
+            .context("Kitsu token request failed")?
+            .error_for_status()
+            .context("Kitsu rejected the credentials")?
+            .json()
+            .await
+            .context("failed to parse Kitsu token response")?;
+
+        Ok(KitsuToken {
+            access_token: resp.access_token,
+            refresh_token: resp.refresh_token,
+            expires_at: Utc::now() + chrono::Duration::seconds(resp.expires_in),
+            user_id,
+        })
+    }
+
+    fn request(&self, builder: RequestBuilder) -> RequestBuilder {
+        builder
+            .bearer_auth(&self.token.access_token)
+            .header("Accept", JSON_API)
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> Result<T> {
+        self.request(self.http.get(format!("{KITSU_API_BASE}{path}")))
+            .query(query)
+            .send()
+            .await
+            .context("Kitsu request failed")?
+            .error_for_status()
+            .context("Kitsu returned an error")?
+            .json()
+            .await
+            .context("failed to parse Kitsu response")
+    }
+
+    async fn current_user_id(&self) -> Result<String> {
+        #[derive(Deserialize)]
+        struct User {}
+
+        let users: Collection<User> = self.get("/users", &[("filter[self]", "true")]).await?;
+        users
+            .data
+            .into_iter()
+            .next()
+            .map(|user| user.id)
+            .ok_or_else(|| anyhow!("Kitsu did not return the signed-in user"))
+    }
+
+    /// The user's library entry for `anime_id`, if the anime is on their list.
+    async fn library_entry(
+        &self,
+        anime_id: u32,
+    ) -> Result<Option<Resource<LibraryEntryAttributes>>> {
+        let anime_id = anime_id.to_string();
+        let entries: Collection<LibraryEntryAttributes> = self
+            .get(
+                "/library-entries",
+                &[
+                    ("filter[userId]", &self.token.user_id),
+                    ("filter[animeId]", &anime_id),
+                ],
+            )
+            .await?;
+        Ok(entries.data.into_iter().next())
+    }
+}
+
+#[async_trait]
+impl SyncBackend for KitsuClient {
+    fn service(&self) -> SyncService {
+        SyncService::Kitsu
+    }
+
+    async fn resolve_anime_id(&self, title: &str) -> Result<Option<u32>> {
+        let results: Collection<AnimeAttributes> = self
+            .get("/anime", &[("filter[text]", title), ("page[limit]", "5")])
+            .await?;
+
+        let Some(first) = results.data.into_iter().next() else {
+            println!("  [sync] No Kitsu results found for \"{title}\". Skipping sync.");
+            return Ok(None);
+        };
+        let id: u32 = first
+            .id
+            .parse()
+            .with_context(|| format!("unexpected Kitsu anime id '{}'", first.id))?;
+
+        let attrs = first.attributes;
+        let en = attrs
+            .titles
+            .en
+            .filter(|t| !t.is_empty())
+            .unwrap_or(attrs.canonical_title);
+        let display = match attrs.titles.ja_jp.filter(|t| !t.is_empty()) {
+            Some(ja) => format!("\"{en}\" ({ja})"),
+            None => format!("\"{en}\""),
+        };
+
+        let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!(
+                "[sync] Found on Kitsu: {display}\n  Is this the correct anime?"
+            ))
+            .default(false)
+            .interact()?;
+
+        if confirmed {
+            Ok(Some(id))
+        } else {
+            println!("  [sync] Skipping Kitsu sync for this show.");
+            Ok(None)
+        }
+    }
+
+    async fn anime_info(&self, id: u32) -> Result<AnimeInfo> {
+        let anime: Single<AnimeAttributes> = self.get(&format!("/anime/{id}"), &[]).await?;
+        let list_status = self.library_entry(id).await?.and_then(|entry| {
+            let status = status_from_kitsu(&entry.attributes.status)?;
+            Some(CurrentListStatus {
+                status: status.as_str().to_string(),
+                num_episodes_watched: entry.attributes.progress,
+            })
+        });
+        Ok(AnimeInfo {
+            list_status,
+            num_episodes: anime.data.attributes.episode_count.unwrap_or(0),
+        })
+    }
+
+    async fn update_anime(&self, id: u32, update: &SyncUpdate) -> Result<()> {
+        let mut attributes = Map::new();
+        attributes.insert("status".into(), json!(status_to_kitsu(update.status)));
+        attributes.insert("progress".into(), json!(update.episode));
+        if let Some(score) = update.score {
+            // Kitsu stores ratings out of 20.
+            attributes.insert("ratingTwenty".into(), json!(u32::from(score) * 2));
+        }
+        if let Some(date) = &update.start_date {
+            attributes.insert("startedAt".into(), json!(format!("{date}T00:00:00.000Z")));
+        }
+        if let Some(date) = &update.finish_date {
+            attributes.insert("finishedAt".into(), json!(format!("{date}T00:00:00.000Z")));
+        }
+
+        let request = match self.library_entry(id).await? {
+            Some(entry) => self
+                .http
+                .patch(format!("{KITSU_API_BASE}/library-entries/{}", entry.id))
+                .json(&json!({
+                    "data": { "id": entry.id, "type": "libraryEntries", "attributes": attributes }
+                })),
+            None => self
+                .http
+                .post(format!("{KITSU_API_BASE}/library-entries"))
+                .json(&json!({
+                    "data": {
+                        "type": "libraryEntries",
+                        "attributes": attributes,
+                        "relationships": {
+                            "anime": { "data": { "type": "anime", "id": id.to_string() } },
+                            "user": { "data": { "type": "users", "id": self.token.user_id } }
+                        }
+                    }
+                })),
+        };
+
+        let resp = self
+            .request(request)
+            .header(CONTENT_TYPE, JSON_API)
+            .send()
+            .await
+            .context("Kitsu library update request failed")?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body: Value = resp.json().await.unwrap_or_default();
+            let detail = body["errors"][0]["detail"]
+                .as_str()
+                .or(body["errors"][0]["title"].as_str())
+                .unwrap_or("no details");
+            bail!("Kitsu returned HTTP {status} on library update: {detail}");
+        }
+        Ok(())
+    }
+}
+
+fn status_to_kitsu(status: WatchStatus) -> &'static str {
+    match status {
+        WatchStatus::Watching => "current",
+        WatchStatus::Completed => "completed",
+        WatchStatus::OnHold => "on_hold",
+        WatchStatus::Dropped => "dropped",
+        WatchStatus::PlanToWatch => "planned",
+    }
+}
+
+fn status_from_kitsu(status: &str) -> Option<WatchStatus> {
+    match status {
+        "current" => Some(WatchStatus::Watching),
+        "completed" => Some(WatchStatus::Completed),
+        "on_hold" => Some(WatchStatus::OnHold),
+        "dropped" => Some(WatchStatus::Dropped),
+        "planned" => Some(WatchStatus::PlanToWatch),
+        _ => None,
+    }
+}
+
+/// Build a KitsuClient only when a stored token exists.
+pub async fn build_kitsu_client_if_enabled() -> Option<KitsuClient> {
+    match KitsuToken::load() {
+        Ok(Some(token)) => match KitsuClient::from_token(token).await {
+            Ok(client) => Some(client),
+            Err(err) => {
+                eprintln!("[sync] Failed to initialize Kitsu client: {err}");
+                None
+            }
+        },
+        Ok(None) => {
+            eprintln!(
+                "[sync] Kitsu sync is enabled but no Kitsu token found. \
+                 Run `anv sync enable --service kitsu` first."
+            );
+            None
+        }
+        Err(err) => {
+            eprintln!("[sync] Failed to load Kitsu token: {err}");
+            None
+        }
+    }
+}
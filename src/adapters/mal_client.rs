@@ -1,4 +1,13 @@
-use crate::config::AppConfig;
+use crate::{
+    adapters::{
+        http::client_builder,
+        sync_backend::{
+            AnimeInfo, CurrentListStatus, CurrentMangaStatus, MangaListInfo, MangaSyncUpdate,
+            SyncBackend, SyncService, SyncUpdate,
+        },
+    },
+    config::AppConfig,
+};
 
 use std::{
     collections::HashMap,
@@ -6,10 +15,10 @@ use std::{
     io::{BufRead, BufReader, Write},
     net::TcpListener,
     path::PathBuf,
-    time::Duration,
 };
 
 use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use dialoguer::{Confirm, theme::ColorfulTheme};
 use dirs_next::data_dir;
@@ -33,62 +42,13 @@ impl MalSyncGateway {
     }
 }
 
-/// Possible watch statuses mirroring MAL's status field.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum WatchStatus {
-    Watching,
-    Completed,
-    OnHold,
-    Dropped,
-    PlanToWatch,
-}
-
-impl WatchStatus {
-    pub fn as_str(self) -> &'static str {
-        match self {
-            WatchStatus::Watching => "watching",
-            WatchStatus::Completed => "completed",
-            WatchStatus::OnHold => "on_hold",
-            WatchStatus::Dropped => "dropped",
-            WatchStatus::PlanToWatch => "plan_to_watch",
-        }
-    }
-
-    pub fn label(self) -> &'static str {
-        match self {
-            WatchStatus::Watching => "Watching",
-            WatchStatus::Completed => "Completed",
-            WatchStatus::OnHold => "On Hold",
-            WatchStatus::Dropped => "Dropped",
-            WatchStatus::PlanToWatch => "Plan to Watch",
-        }
-    }
-}
-
-/// Data passed when updating MAL status.
-#[derive(Debug, Clone)]
-pub struct SyncUpdate {
-    /// Anime title as displayed in anv (from AllAnime).
-    pub title: String,
-    /// Current episode just watched (1-indexed integer).
-    pub episode: u32,
-    /// Total number of episodes if known (used to decide Completed vs Watching).
-    pub total_episodes: Option<u32>,
-    pub status: WatchStatus,
-    /// YYYY-MM-DD: set when first adding the anime to the list (not-on-list → watching).
-    pub start_date: Option<String>,
-    /// YYYY-MM-DD: set when the anime is marked completed.
-    pub finish_date: Option<String>,
-    /// User rating 1–10 to submit to MAL. None means no score change.
-    pub score: Option<u8>,
-}
-
 const MAL_AUTH_URL: &str = "https://myanimelist.net/v1/oauth2/authorize";
 const MAL_TOKEN_URL: &str = "https://myanimelist.net/v1/oauth2/token";
 const MAL_API_BASE: &str = "https://api.myanimelist.net/v2";
 const OAUTH_PORT: u16 = 11422;
 const OAUTH_REDIRECT_URI: &str = "http://localhost:11422/callback";
 const CODE_VERIFIER_LEN: usize = 64;
+const USER_AGENT: &str = concat!("anv/", env!("CARGO_PKG_VERSION"));
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MalToken {
@@ -134,48 +94,6 @@ impl MalToken {
     }
 }
 
-/// Persistent cache that maps AllAnime show IDs to MAL anime IDs.
-/// The confirmation dialog is shown only for IDs not yet in this cache.
-#[derive(Debug, Default, Serialize, Deserialize)]
-pub struct MalIdCache {
-    entries: HashMap<String, u32>,
-}
-
-impl MalIdCache {
-    fn cache_path() -> Result<PathBuf> {
-        let base = data_dir().ok_or_else(|| anyhow!("Could not determine data directory"))?;
-        Ok(base.join("anv").join("mal_id_cache.json"))
-    }
-
-    pub fn load() -> Result<Self> {
-        let path = Self::cache_path()?;
-        if !path.exists() {
-            return Ok(Self::default());
-        }
-        let data = fs::read_to_string(&path)
-            .with_context(|| format!("failed to read ID cache {}", path.display()))?;
-        serde_json::from_str(&data)
-            .with_context(|| format!("failed to parse ID cache {}", path.display()))
-    }
-
-    pub fn get(&self, allanime_id: &str) -> Option<u32> {
-        self.entries.get(allanime_id).copied()
-    }
-
-    pub fn insert_and_save(&mut self, allanime_id: &str, mal_id: u32) -> Result<()> {
-        self.entries.insert(allanime_id.to_string(), mal_id);
-        let path = Self::cache_path()?;
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)
-                .with_context(|| format!("failed to create data directory {}", parent.display()))?;
-        }
-        let data = serde_json::to_string_pretty(self).context("failed to serialize ID cache")?;
-        fs::write(&path, data)
-            .with_context(|| format!("failed to write ID cache to {}", path.display()))?;
-        Ok(())
-    }
-}
-
 fn generate_code_verifier() -> String {
     const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-._~";
     (0..CODE_VERIFIER_LEN)
@@ -226,8 +144,7 @@ impl MalClient {
     /// Build a `MalClient` from an existing (possibly expired) token.
     /// Call `MalClient::authenticate` first if no token exists.
     pub async fn from_token(client_id: String, mut token: MalToken) -> Result<Self> {
-        let http = Client::builder()
-            .timeout(Duration::from_secs(30))
+        let http = client_builder(USER_AGENT)
             .build()
             .context("failed to build HTTP client")?;
 
@@ -273,8 +190,7 @@ impl MalClient {
 
         println!("Authorization code received. Exchanging for token...");
 
-        let http = Client::builder()
-            .timeout(Duration::from_secs(30))
+        let http = client_builder(USER_AGENT)
             .build()
             .context("failed to build HTTP client")?;
 
@@ -399,9 +315,19 @@ impl MalClient {
     /// and ask the user to confirm it's the right anime.
     /// Returns `Some(mal_id)` if confirmed, `None` if user declines.
     pub async fn resolve_and_confirm_mal_id(&self, title: &str) -> Result<Option<u32>> {
+        self.search_and_confirm(MalMedia::Anime, title).await
+    }
+
+    /// Like [`MalClient::resolve_and_confirm_mal_id`], against MAL's manga list.
+    pub async fn resolve_and_confirm_mal_manga_id(&self, title: &str) -> Result<Option<u32>> {
+        self.search_and_confirm(MalMedia::Manga, title).await
+    }
+
+    async fn search_and_confirm(&self, media: MalMedia, title: &str) -> Result<Option<u32>> {
+        let kind = media.as_str();
         let resp = self
             .http
-            .get(format!("{MAL_API_BASE}/anime"))
+            .get(format!("{MAL_API_BASE}/{kind}"))
             .bearer_auth(&self.token.access_token)
             .query(&[
                 ("q", title),
@@ -410,12 +336,12 @@ impl MalClient {
             ])
             .send()
             .await
-            .context("MAL anime search request failed")?
+            .with_context(|| format!("MAL {kind} search request failed"))?
             .error_for_status()
-            .context("MAL returned error on anime search")?
+            .with_context(|| format!("MAL returned error on {kind} search"))?
             .json::<AnimeSearchResponse>()
             .await
-            .context("failed to parse MAL anime search response")?;
+            .with_context(|| format!("failed to parse MAL {kind} search response"))?;
 
         let Some(first) = resp.data.into_iter().next() else {
             println!("  [sync] No MAL results found for \"{title}\". Skipping sync.");
@@ -438,7 +364,7 @@ impl MalClient {
 
         let confirmed = Confirm::with_theme(&ColorfulTheme::default())
             .with_prompt(format!(
-                "[sync] Found on MAL: {display}\n  Is this the correct anime?"
+                "[sync] Found on MAL: {display}\n  Is this the correct {kind}?"
             ))
             .default(false)
             .interact()?;
@@ -446,7 +372,7 @@ impl MalClient {
         if confirmed {
             Ok(Some(detail.id))
         } else {
-            println!("  [sync] Skipping MAL sync for this show.");
+            println!("  [sync] Skipping MAL sync for this {}.", media.noun());
             Ok(None)
         }
     }
@@ -521,32 +447,202 @@ impl MalClient {
     }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct CurrentListStatus {
+impl MalClient {
+    /// Update MAL manga list status.
+    pub async fn update_manga_status_with_id(
+        &self,
+        mal_id: u32,
+        update: &MangaSyncUpdate,
+    ) -> Result<()> {
+        let mut form: HashMap<&str, String> = HashMap::new();
+        form.insert("status", update.status.as_str().to_string());
+        form.insert("num_chapters_read", update.chapter.to_string());
+        if let Some(ref d) = update.start_date {
+            form.insert("start_date", d.clone());
+        }
+        if let Some(ref d) = update.finish_date {
+            form.insert("finish_date", d.clone());
+        }
+        if let Some(score) = update.score {
+            form.insert("score", score.to_string());
+        }
+
+        self.http
+            .patch(format!("{MAL_API_BASE}/manga/{mal_id}/my_list_status"))
+            .bearer_auth(&self.token.access_token)
+            .form(&form)
+            .send()
+            .await
+            .context("MAL update manga list status request failed")?
+            .error_for_status()
+            .context("MAL returned error on manga list status update")?;
+
+        Ok(())
+    }
+
+    /// Fetch the manga's list status and chapter count in one request.
+    /// `num_chapters == 0` means MAL doesn't know the total yet.
+    pub async fn get_manga_info(&self, mal_id: u32) -> Result<MangaListInfo> {
+        #[derive(Deserialize)]
+        struct MangaWithStatus {
+            my_list_status: Option<CurrentMangaStatus>,
+            #[serde(default)]
+            num_chapters: u32,
+        }
+
+        let resp = self
+            .http
+            .get(format!("{MAL_API_BASE}/manga/{mal_id}"))
+            .bearer_auth(&self.token.access_token)
+            .query(&[("fields", "my_list_status,num_chapters")])
+            .send()
+            .await
+            .context("MAL get manga info request failed")?
+            .error_for_status()
+            .context("MAL returned error fetching manga info")?
+            .json::<MangaWithStatus>()
+            .await
+            .context("failed to parse MAL manga info response")?;
+
+        Ok(MangaListInfo {
+            list_status: resp.my_list_status,
+            num_chapters: resp.num_chapters,
+        })
+    }
+}
+
+/// One entry of the user's MAL anime list, as returned by [`MalClient::fetch_anime_list`].
+#[derive(Debug, Clone)]
+pub struct MalListEntry {
+    pub mal_id: u32,
+    pub title: String,
+    /// English title, when MAL has one.
+    pub english_title: Option<String>,
     pub status: String,
     pub num_episodes_watched: u32,
+    pub updated_at: DateTime<Utc>,
 }
 
-/// Combined per-anime info returned by [`MalClient::get_anime_info`].
-pub struct AnimeInfo {
-    /// The user's current list entry, or `None` if not on their list.
-    pub list_status: Option<CurrentListStatus>,
-    /// MAL's planned total episode count. `0` means unknown / still airing.
-    pub num_episodes: u32,
+impl MalClient {
+    /// The user's anime list entries with `status` (`watching`,
+    /// `plan_to_watch`, ...), following MAL's pagination.
+    pub async fn fetch_anime_list(&self, status: &str) -> Result<Vec<MalListEntry>> {
+        #[derive(Deserialize)]
+        struct Page {
+            data: Vec<Item>,
+            #[serde(default)]
+            paging: Paging,
+        }
+        #[derive(Deserialize, Default)]
+        struct Paging {
+            next: Option<String>,
+        }
+        #[derive(Deserialize)]
+        struct Item {
+            node: AnimeDetail,
+            list_status: ListStatus,
+        }
+        #[derive(Deserialize)]
+        struct ListStatus {
+            status: String,
+            #[serde(default)]
+            num_episodes_watched: u32,
+            updated_at: DateTime<Utc>,
+        }
+
+        let mut entries = Vec::new();
+        let mut request = self
+            .http
+            .get(format!("{MAL_API_BASE}/users/@me/animelist"))
+            .query(&[
+                ("status", status),
+                ("fields", "list_status,alternative_titles"),
+                ("limit", "100"),
+                ("nsfw", "true"),
+            ]);
+        loop {
+            let page = request
+                .bearer_auth(&self.token.access_token)
+                .send()
+                .await
+                .context("MAL anime list request failed")?
+                .error_for_status()
+                .context("MAL returned error fetching the anime list")?
+                .json::<Page>()
+                .await
+                .context("failed to parse MAL anime list response")?;
+
+            entries.extend(page.data.into_iter().map(|item| MalListEntry {
+                mal_id: item.node.id,
+                english_title: Some(item.node.alternative_titles.en).filter(|t| !t.is_empty()),
+                title: item.node.title,
+                status: item.list_status.status,
+                num_episodes_watched: item.list_status.num_episodes_watched,
+                updated_at: item.list_status.updated_at,
+            }));
+            match page.paging.next {
+                Some(next) => request = self.http.get(next),
+                None => return Ok(entries),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum MalMedia {
+    Anime,
+    Manga,
 }
 
-/// Returns `true` when a user confirmation prompt is needed before syncing.
-///
-/// Prompt required when:
-/// - Anime is not on the user's list yet (first time → Watching)
-/// - Status is changing (Watching → Completed, etc.)
-///
-/// Silent update when:
-/// - Already Watching and we're just advancing the episode count
-pub fn should_confirm_sync(current: &Option<CurrentListStatus>, new_status: WatchStatus) -> bool {
-    match current {
-        None => true,
-        Some(cur) => cur.status != new_status.as_str(),
+impl MalMedia {
+    fn as_str(self) -> &'static str {
+        match self {
+            MalMedia::Anime => "anime",
+            MalMedia::Manga => "manga",
+        }
+    }
+
+    /// What the confirmation messages call a title of this kind.
+    fn noun(self) -> &'static str {
+        match self {
+            MalMedia::Anime => "show",
+            MalMedia::Manga => "manga",
+        }
+    }
+}
+
+#[async_trait]
+impl SyncBackend for MalClient {
+    fn service(&self) -> SyncService {
+        SyncService::Mal
+    }
+
+    async fn resolve_anime_id(&self, title: &str) -> Result<Option<u32>> {
+        self.resolve_and_confirm_mal_id(title).await
+    }
+
+    async fn anime_info(&self, id: u32) -> Result<AnimeInfo> {
+        self.get_anime_info(id).await
+    }
+
+    async fn update_anime(&self, id: u32, update: &SyncUpdate) -> Result<()> {
+        self.update_status_with_id(id, update).await
+    }
+
+    fn supports_manga(&self) -> bool {
+        true
+    }
+
+    async fn resolve_manga_id(&self, title: &str) -> Result<Option<u32>> {
+        self.resolve_and_confirm_mal_manga_id(title).await
+    }
+
+    async fn manga_info(&self, id: u32) -> Result<MangaListInfo> {
+        self.get_manga_info(id).await
+    }
+
+    async fn update_manga(&self, id: u32, update: &MangaSyncUpdate) -> Result<()> {
+        self.update_manga_status_with_id(id, update).await
     }
 }
 
@@ -1,4 +1,16 @@
+pub mod anilist_client;
+pub mod downloader;
+pub mod export;
+pub mod hls;
+pub mod http;
+pub mod kitsu_client;
 pub mod mal_client;
+pub mod mpv_ipc;
 pub mod player;
+pub mod player_backend;
 pub mod providers;
 pub mod reader;
+pub mod remote_id_cache;
+pub mod stream_policy;
+pub mod sync_backend;
+pub mod sync_queue;
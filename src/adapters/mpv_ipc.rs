@@ -0,0 +1,238 @@
+use crate::types::SubtitleTrack;
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde_json::{Value, json};
+use std::{path::PathBuf, time::Duration};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    time::timeout,
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+const RESUME_MIN_SECS: f64 = 5.0;
+const RESUME_END_MARGIN_SECS: f64 = 10.0;
+const RESUME_END_PERCENT: f64 = 98.0;
+
+/// Where mpv was when it was last polled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackProgress {
+    /// Seconds from the start of the file (`time-pos`).
+    pub position: f64,
+    /// Total length in seconds (`duration`), when known.
+    pub duration: Option<f64>,
+    /// Percentage watched (`percent-pos`), when known.
+    pub percent: Option<f64>,
+}
+
+impl PlaybackProgress {
+    /// Share of the file played, from `percent-pos` or `time-pos / duration`.
+    pub fn watched_percent(&self) -> Option<f64> {
+        self.percent.or_else(|| {
+            self.duration
+                .filter(|d| *d > 0.0)
+                .map(|d| self.position * 100.0 / d)
+        })
+    }
+
+    /// Position worth resuming from, or `None` when playback barely started
+    /// or reached the end credits.
+    pub fn resume_position(&self) -> Option<f64> {
+        let near_end = self.percent.is_some_and(|p| p >= RESUME_END_PERCENT)
+            || self
+                .duration
+                .is_some_and(|d| d - self.position < RESUME_END_MARGIN_SECS);
+        (self.position >= RESUME_MIN_SECS && !near_end).then_some(self.position)
+    }
+}
+
+/// Snapshot of a (possibly multi-file) mpv session.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackState {
+    /// Index of the playlist entry being played (`playlist-pos`).
+    pub playlist_pos: Option<usize>,
+    pub progress: Option<PlaybackProgress>,
+}
+
+/// Per-process IPC endpoint passed to mpv via `--input-ipc-server`.
+pub fn ipc_server_path() -> PathBuf {
+    let name = format!("anv-mpv-{}", std::process::id());
+    if cfg!(windows) {
+        PathBuf::from(format!(r"\\.\pipe\{name}"))
+    } else {
+        std::env::temp_dir().join(format!("{name}.sock"))
+    }
+}
+
+/// Minimal client for mpv's JSON IPC protocol.
+pub struct MpvIpc {
+    reader: BufReader<Box<dyn AsyncRead + Unpin + Send>>,
+    writer: Box<dyn AsyncWrite + Unpin + Send>,
+    next_request_id: u64,
+}
+
+impl MpvIpc {
+    #[cfg(unix)]
+    pub async fn connect(path: &std::path::Path) -> Result<Self> {
+        let stream = tokio::net::UnixStream::connect(path)
+            .await
+            .with_context(|| format!("failed to connect to mpv IPC at {}", path.display()))?;
+        let (read, write) = stream.into_split();
+        Ok(Self::from_parts(Box::new(read), Box::new(write)))
+    }
+
+    #[cfg(windows)]
+    pub async fn connect(path: &std::path::Path) -> Result<Self> {
+        let pipe = tokio::net::windows::named_pipe::ClientOptions::new()
+            .open(path)
+            .with_context(|| format!("failed to connect to mpv IPC at {}", path.display()))?;
+        let (read, write) = tokio::io::split(pipe);
+        Ok(Self::from_parts(Box::new(read), Box::new(write)))
+    }
+
+    fn from_parts(
+        read: Box<dyn AsyncRead + Unpin + Send>,
+        writer: Box<dyn AsyncWrite + Unpin + Send>,
+    ) -> Self {
+        Self {
+            reader: BufReader::new(read),
+            writer,
+            next_request_id: 1,
+        }
+    }
+
+    /// Sends `command` and waits for its reply, skipping unrelated events.
+    /// Returns the reply's `data` field (`Null` when the command has none).
+    pub async fn command(&mut self, command: &[Value]) -> Result<Value> {
+        let reply = self.request(command).await?;
+        match reply.get("error").and_then(Value::as_str) {
+            Some("success") | None => Ok(reply.get("data").cloned().unwrap_or(Value::Null)),
+            Some(err) => Err(anyhow!("mpv IPC error: {err}")),
+        }
+    }
+
+    /// Reads a numeric property; `None` when mpv has no value for it yet
+    /// (e.g. `duration` while the file is still loading).
+    pub async fn get_f64(&mut self, property: &str) -> Result<Option<f64>> {
+        let reply = self
+            .request(&[json!("get_property"), json!(property)])
+            .await?;
+        match reply.get("error").and_then(Value::as_str) {
+            Some("success") | None => Ok(reply.get("data").and_then(Value::as_f64)),
+            Some("property unavailable") => Ok(None),
+            Some(err) => Err(anyhow!("mpv IPC error: {err}")),
+        }
+    }
+
+    /// Writes one request and returns the raw reply carrying its `request_id`.
+    async fn request(&mut self, command: &[Value]) -> Result<Value> {
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+        let mut line = json!({ "command": command, "request_id": request_id }).to_string();
+        line.push('\n');
+        self.writer
+            .write_all(line.as_bytes())
+            .await
+            .context("failed to write to mpv IPC")?;
+
+        timeout(REQUEST_TIMEOUT, async {
+            let mut reply = String::new();
+            loop {
+                reply.clear();
+                if self.reader.read_line(&mut reply).await? == 0 {
+                    bail!("mpv closed the IPC connection");
+                }
+                let Ok(message) = serde_json::from_str::<Value>(&reply) else {
+                    continue;
+                };
+                if message.get("request_id").and_then(Value::as_u64) == Some(request_id) {
+                    return Ok(message);
+                }
+            }
+        })
+        .await
+        .context("timed out waiting for mpv IPC reply")?
+    }
+
+    /// Appends `url` to the playlist, starting it right away if mpv is idle.
+    /// `options` are applied to that entry only (e.g. its media title).
+    pub async fn append_file(&mut self, url: &str, options: &[(String, String)]) -> Result<()> {
+        let options = options
+            .iter()
+            .map(|(key, value)| format!("{key}=%{}%{value}", value.len()))
+            .collect::<Vec<_>>()
+            .join(",");
+        // mpv 0.38 added an insertion index before the options argument.
+        let with_index = [
+            json!("loadfile"),
+            json!(url),
+            json!("append-play"),
+            json!(-1),
+            json!(options),
+        ];
+        // Older mpv rejects the extra argument; anything else (a dead socket,
+        // a timeout) would fail the retry the same way, so it is returned.
+        let reply = self.request(&with_index).await?;
+        match reply.get("error").and_then(Value::as_str) {
+            Some("success") | None => return Ok(()),
+            Some("invalid parameter") => {}
+            Some(err) => bail!("mpv IPC error: {err}"),
+        }
+        self.command(&[
+            json!("loadfile"),
+            json!(url),
+            json!("append-play"),
+            json!(options),
+        ])
+        .await
+        .map(|_| ())
+    }
+
+    /// Loads an external subtitle for the current file, labelled with its
+    /// language; `select` also makes it the active track.
+    pub async fn add_subtitle(&mut self, track: &SubtitleTrack, select: bool) -> Result<()> {
+        let flag = if select { "select" } else { "auto" };
+        let title = track
+            .label
+            .as_deref()
+            .or(track.lang.as_deref())
+            .unwrap_or("");
+        let lang = track.lang.as_deref().unwrap_or("");
+        self.command(&[
+            json!("sub-add"),
+            json!(track.url),
+            json!(flag),
+            json!(title),
+            json!(lang),
+        ])
+        .await
+        .map(|_| ())
+    }
+
+    /// Polls the playlist position along with the playback progress.
+    pub async fn state(&mut self) -> Result<PlaybackState> {
+        let playlist_pos = self
+            .get_f64("playlist-pos")
+            .await?
+            .filter(|pos| *pos >= 0.0)
+            .map(|pos| pos as usize);
+        let progress = self.progress().await?;
+        Ok(PlaybackState {
+            playlist_pos,
+            progress,
+        })
+    }
+
+    /// Polls `time-pos`, `duration` and `percent-pos` in one go.
+    pub async fn progress(&mut self) -> Result<Option<PlaybackProgress>> {
+        let Some(position) = self.get_f64("time-pos").await? else {
+            return Ok(None);
+        };
+        let duration = self.get_f64("duration").await?;
+        let percent = self.get_f64("percent-pos").await?;
+        Ok(Some(PlaybackProgress {
+            position,
+            duration,
+            percent,
+        }))
+    }
+}
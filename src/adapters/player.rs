@@ -1,18 +1,40 @@
-use crate::types::StreamOption;
+use crate::{
+    adapters::{
+        mpv_ipc::{MpvIpc, PlaybackProgress, PlaybackState, ipc_server_path},
+        player_backend::{LaunchRequest, PlayerBackend, mpv_file_options},
+    },
+    types::{StreamOption, SubtitleTrack},
+};
 
 use anyhow::{Result, anyhow, bail};
 use dialoguer::{Select, theme::ColorfulTheme};
-use tokio::process::Command;
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use tokio::{
+    process::{Child, Command},
+    time::Interval,
+};
 
 pub const PLAYER_ENV_KEY: &str = "ANV_PLAYER";
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 pub struct DefaultPlayerGateway;
 
-pub fn detect_player() -> String {
-    std::env::var(PLAYER_ENV_KEY)
-        .ok()
-        .filter(|val| !val.trim().is_empty())
-        .unwrap_or_else(|| "mpv".to_string())
+/// How a player session ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerExit {
+    /// Playback finished or the user quit the player.
+    Finished,
+    /// The player could not play the stream (dead CDN link, 403, ...), so
+    /// another stream may succeed.
+    StreamFailed,
+}
+
+/// What happened during a player session.
+#[derive(Debug, Clone, Copy)]
+pub struct PlaybackOutcome {
+    pub exit: PlayerExit,
+    /// Last position reported over IPC; `None` if the player never reported one.
+    pub progress: Option<PlaybackProgress>,
 }
 
 pub(crate) fn build_command(player: &str) -> Result<Command> {
@@ -28,68 +50,186 @@ pub(crate) fn build_command(player: &str) -> Result<Command> {
 }
 
 pub fn choose_stream(mut options: Vec<StreamOption>) -> Result<Option<StreamOption>> {
-    if options.len() == 1 {
-        return Ok(Some(options.remove(0)));
+    Ok(choose_stream_index(&options)?.map(|idx| options.remove(idx)))
+}
+
+pub fn choose_stream_index(options: &[StreamOption]) -> Result<Option<usize>> {
+    if options.len() <= 1 {
+        return Ok((!options.is_empty()).then_some(0));
     }
     let labels: Vec<String> = options.iter().map(StreamOption::label).collect();
-    let selection = Select::with_theme(&ColorfulTheme::default())
+    Ok(Select::with_theme(&ColorfulTheme::default())
         .with_prompt("Select a stream")
         .items(&labels)
         .default(0)
-        .interact_opt()?;
-    let Some(idx) = selection else {
-        return Ok(None);
-    };
-    Ok(Some(options.remove(idx)))
+        .interact_opt()?)
 }
 
+/// Plays `stream`, optionally starting `start` seconds in, and tracks the
+/// playback position over mpv's JSON IPC until the player exits.
 pub async fn launch_player(
     stream: &StreamOption,
     title: &str,
     episode: &str,
-    player: &str,
-) -> Result<()> {
-    let mut cmd = build_command(player)?;
-    let media_title = format!("{title} - Episode {episode}");
-    cmd.arg("--quiet");
-    cmd.arg("--terminal=no");
-    cmd.arg(format!("--force-media-title={media_title}"));
-    if let Some(sub) = &stream.subtitle {
-        cmd.arg(format!("--sub-file={sub}"));
-    }
-    for (key, value) in &stream.headers {
-        if key.eq_ignore_ascii_case("user-agent") {
-            cmd.arg(format!("--user-agent={value}"));
-        } else if key.eq_ignore_ascii_case("referer") {
-            cmd.arg(format!("--referrer={value}"));
-            cmd.arg(format!("--http-header-fields=Referer: {value}"));
-        } else {
-            cmd.arg(format!("--http-header-fields={}: {value}", key));
+    player: &Arc<dyn PlayerBackend>,
+    start: Option<f64>,
+) -> Result<PlaybackOutcome> {
+    let mut session = PlayerSession::start(stream, title, episode, player, start)?;
+    let mut progress = None;
+    loop {
+        match session.next_event().await? {
+            SessionEvent::Progress(state) => {
+                if state.progress.is_some() {
+                    progress = state.progress;
+                }
+            }
+            SessionEvent::Exited(exit) => return Ok(PlaybackOutcome { exit, progress }),
         }
     }
-    cmd.arg(&stream.url);
-
-    let status = match cmd.status().await {
-        Ok(status) => status,
-        Err(err) => {
-            if err.kind() == std::io::ErrorKind::NotFound {
-                let bin = shlex::split(player)
-                    .and_then(|v| v.into_iter().next())
-                    .unwrap_or_else(|| player.to_string());
-                return Err(anyhow!(
-                    "Player binary '{}' not found. Install it or set {} to a valid command.",
-                    bin,
-                    PLAYER_ENV_KEY
-                ));
+}
+
+/// Something the player reported while running.
+#[derive(Debug, Clone, Copy)]
+pub enum SessionEvent {
+    /// Periodic update, sent once a second while the IPC socket is reachable.
+    Progress(PlaybackState),
+    Exited(PlayerExit),
+}
+
+/// A running player and, for mpv, its IPC connection. Further episodes can
+/// be appended to mpv's playlist, so one window survives a whole binge session.
+pub struct PlayerSession {
+    child: Child,
+    backend: Arc<dyn PlayerBackend>,
+    ipc: Option<MpvIpc>,
+    ipc_path: Option<PathBuf>,
+    ticker: Interval,
+}
+
+impl PlayerSession {
+    /// Spawns the player on `stream`, optionally starting `start` seconds in.
+    pub fn start(
+        stream: &StreamOption,
+        title: &str,
+        episode: &str,
+        backend: &Arc<dyn PlayerBackend>,
+        start: Option<f64>,
+    ) -> Result<Self> {
+        let ipc_path = backend.supports_ipc().then(ipc_server_path);
+        let media_title = format!("{title} - Episode {episode}");
+        let mut cmd = backend.command(&LaunchRequest {
+            title: &media_title,
+            urls: vec![stream.url.clone()],
+            subtitles: &stream.subtitles,
+            headers: &stream.headers,
+            start,
+            images: false,
+            ipc_server: ipc_path.as_deref(),
+        })?;
+        // A session dropped early (an error mid-binge) must not leave the
+        // player window behind; tokio reaps the killed process.
+        cmd.kill_on_drop(true);
+
+        let child = match cmd.spawn() {
+            Ok(child) => child,
+            Err(err) => {
+                let bin = cmd.as_std().get_program().to_string_lossy().into_owned();
+                if err.kind() == std::io::ErrorKind::NotFound {
+                    return Err(anyhow!(
+                        "Player binary '{}' not found. Install it or set {} to a valid command.",
+                        bin,
+                        PLAYER_ENV_KEY
+                    ));
+                }
+                return Err(anyhow!(err).context(format!("failed to launch player '{bin}'")));
+            }
+        };
+
+        Ok(Self {
+            child,
+            backend: Arc::clone(backend),
+            ipc: None,
+            ipc_path,
+            ticker: tokio::time::interval(PROGRESS_POLL_INTERVAL),
+        })
+    }
+
+    /// Waits for the next position update or for the player to exit.
+    /// IPC failures are not fatal: playback continues, only tracking is lost.
+    pub async fn next_event(&mut self) -> Result<SessionEvent> {
+        loop {
+            tokio::select! {
+                status = self.child.wait() => {
+                    let status =
+                        status.map_err(|err| anyhow!(err).context("failed to wait for player"))?;
+                    return match self.backend.exit_kind(status) {
+                        Some(exit) => Ok(SessionEvent::Exited(exit)),
+                        None => bail!("player exited with status {status}"),
+                    };
+                }
+                _ = self.ticker.tick(), if self.ipc_path.is_some() => {
+                    let Some(ipc) = self.ipc().await else {
+                        continue;
+                    };
+                    match ipc.state().await {
+                        Ok(state) => return Ok(SessionEvent::Progress(state)),
+                        Err(_) => self.ipc = None,
+                    }
+                }
             }
-            return Err(anyhow!(err).context(format!("failed to launch player '{player}'")));
         }
-    };
+    }
+
+    /// Queues `stream` after the current playlist entry; mpv moves on to it
+    /// when the current one ends.
+    pub async fn append(
+        &mut self,
+        stream: &StreamOption,
+        title: &str,
+        episode: &str,
+        start: Option<f64>,
+    ) -> Result<()> {
+        let media_title = format!("{title} - Episode {episode}");
+        let mut options = mpv_file_options(&media_title, &stream.headers);
+        options.push(("start".to_string(), format!("{:.1}", start.unwrap_or(0.0))));
+        self.connected_ipc()
+            .await?
+            .append_file(&stream.url, &options)
+            .await
+    }
+
+    /// Loads external subtitles for the entry that is playing now and shows
+    /// the first (preferred) one.
+    pub async fn add_subtitles(&mut self, tracks: &[SubtitleTrack]) -> Result<()> {
+        let ipc = self.connected_ipc().await?;
+        for (idx, track) in tracks.iter().enumerate() {
+            ipc.add_subtitle(track, idx == 0).await?;
+        }
+        Ok(())
+    }
 
-    if !status.success() {
-        bail!("player exited with status {status}");
+    async fn ipc(&mut self) -> Option<&mut MpvIpc> {
+        if self.ipc.is_none() {
+            let path = self.ipc_path.as_deref()?;
+            self.ipc = MpvIpc::connect(path).await.ok();
+        }
+        self.ipc.as_mut()
+    }
+
+    async fn connected_ipc(&mut self) -> Result<&mut MpvIpc> {
+        self.ipc()
+            .await
+            .ok_or_else(|| anyhow!("player IPC is not available"))
+    }
+}
+
+impl Drop for PlayerSession {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(path) = &self.ipc_path {
+            let _ = std::fs::remove_file(path);
+        }
     }
-    Ok(())
 }
 
 impl DefaultPlayerGateway {
@@ -97,13 +237,18 @@ impl DefaultPlayerGateway {
         choose_stream(options)
     }
 
+    pub fn choose_stream_index(&self, options: &[StreamOption]) -> Result<Option<usize>> {
+        choose_stream_index(options)
+    }
+
     pub async fn launch_player(
         &self,
         stream: &StreamOption,
         title: &str,
         episode: &str,
-        player: &str,
-    ) -> Result<()> {
-        launch_player(stream, title, episode, player).await
+        player: &Arc<dyn PlayerBackend>,
+        start: Option<f64>,
+    ) -> Result<PlaybackOutcome> {
+        launch_player(stream, title, episode, player, start).await
     }
 }
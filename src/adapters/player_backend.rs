@@ -0,0 +1,289 @@
+use crate::{
+    adapters::player::{PlayerExit, build_command},
+    types::SubtitleTrack,
+};
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    path::{Path, PathBuf},
+    process::ExitStatus,
+    sync::Arc,
+};
+use tokio::process::Command;
+
+/// What to open in the player, independent of its command-line syntax.
+pub struct LaunchRequest<'a> {
+    /// Window/media title, e.g. "Frieren - Episode 3".
+    pub title: &'a str,
+    /// Files or URLs to play, in playlist order.
+    pub urls: Vec<String>,
+    /// External subtitle tracks, preferred first.
+    pub subtitles: &'a [SubtitleTrack],
+    /// HTTP headers the CDN expects (referer, user agent, ...).
+    pub headers: &'a HashMap<String, String>,
+    /// Seconds into the first file to start from.
+    pub start: Option<f64>,
+    /// Keep images on screen until the user moves on (manga pages).
+    pub images: bool,
+    /// mpv JSON IPC endpoint; only honoured by backends that support it.
+    pub ipc_server: Option<&'a Path>,
+}
+
+/// Translates a [`LaunchRequest`] into one player's command line.
+pub trait PlayerBackend: Debug + Send + Sync {
+    fn command(&self, request: &LaunchRequest) -> Result<Command>;
+
+    /// Whether the player speaks mpv's JSON IPC, which position tracking,
+    /// resume and the shared binge playlist rely on.
+    fn supports_ipc(&self) -> bool {
+        false
+    }
+
+    /// Classifies the exit status; `None` means the player itself failed.
+    fn exit_kind(&self, status: ExitStatus) -> Option<PlayerExit> {
+        match status.code() {
+            Some(0) | None => Some(PlayerExit::Finished),
+            Some(_) => Some(PlayerExit::StreamFailed),
+        }
+    }
+}
+
+/// Which [`PlayerBackend`] drives the configured `player` command.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlayerBackendKind {
+    /// Template if the command has a `{url}` placeholder, VLC for `vlc`/`cvlc`,
+    /// mpv otherwise.
+    #[default]
+    Auto,
+    Mpv,
+    Vlc,
+    Template,
+}
+
+/// Builds the backend for `player` (a command line, or an argv template).
+pub fn select_backend(player: &str, kind: PlayerBackendKind) -> Arc<dyn PlayerBackend> {
+    let player = player.to_string();
+    match kind {
+        PlayerBackendKind::Auto if player.contains("{url}") => Arc::new(TemplateBackend(player)),
+        PlayerBackendKind::Auto if is_vlc(&player) => Arc::new(VlcBackend(player)),
+        PlayerBackendKind::Auto | PlayerBackendKind::Mpv => Arc::new(MpvBackend(player)),
+        PlayerBackendKind::Vlc => Arc::new(VlcBackend(player)),
+        PlayerBackendKind::Template => Arc::new(TemplateBackend(player)),
+    }
+}
+
+fn is_vlc(player: &str) -> bool {
+    shlex::split(player)
+        .and_then(|parts| parts.into_iter().next())
+        .and_then(|bin| {
+            PathBuf::from(bin)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_ascii_lowercase())
+        })
+        .is_some_and(|stem| stem == "vlc" || stem == "cvlc")
+}
+
+#[derive(Debug)]
+pub struct MpvBackend(String);
+
+impl PlayerBackend for MpvBackend {
+    fn command(&self, request: &LaunchRequest) -> Result<Command> {
+        let mut cmd = build_command(&self.0)?;
+        cmd.arg("--quiet");
+        cmd.arg("--terminal=no");
+        if let Some(ipc_server) = request.ipc_server {
+            cmd.arg("--prefetch-playlist=yes");
+            cmd.arg(format!("--input-ipc-server={}", ipc_server.display()));
+        }
+        if let Some(start) = request.start {
+            cmd.arg(format!("--start={start:.1}"));
+        }
+        if request.images {
+            cmd.arg("--image-display-duration=inf");
+        }
+        for (key, value) in mpv_file_options(request.title, request.headers) {
+            cmd.arg(format!("--{key}={value}"));
+        }
+        if request.subtitles.is_empty() {
+            cmd.args(&request.urls);
+            return Ok(cmd);
+        }
+        // `--sub-file` is global, so it would also load these tracks for every
+        // episode appended over IPC later; a `--{ ... --}` group scopes them
+        // to the files given here.
+        cmd.arg("--{");
+        for sub in request.subtitles {
+            cmd.arg(format!("--sub-file={}", sub.url));
+        }
+        // Show the preferred track rather than whichever mpv would pick.
+        if let Some(lang) = request
+            .subtitles
+            .iter()
+            .find(|sub| sub.default)
+            .and_then(|sub| sub.lang.as_deref())
+        {
+            cmd.arg(format!("--slang={lang}"));
+        }
+        cmd.args(&request.urls);
+        cmd.arg("--}");
+        Ok(cmd)
+    }
+
+    fn supports_ipc(&self) -> bool {
+        true
+    }
+
+    /// 0 is a normal exit, 4 a quit by signal or key, 2 and 3 mean some or
+    /// all files could not be played.
+    fn exit_kind(&self, status: ExitStatus) -> Option<PlayerExit> {
+        match status.code() {
+            Some(0) | Some(4) => Some(PlayerExit::Finished),
+            Some(2) | Some(3) => Some(PlayerExit::StreamFailed),
+            _ => None,
+        }
+    }
+}
+
+/// mpv options that belong to one playlist entry: its title and the HTTP
+/// headers its CDN expects. Shared by the command line and IPC `loadfile`.
+pub(crate) fn mpv_file_options(
+    title: &str,
+    headers: &HashMap<String, String>,
+) -> Vec<(String, String)> {
+    let mut options = vec![("force-media-title".to_string(), title.to_string())];
+    let mut header_fields = Vec::new();
+    for (key, value) in headers {
+        if key.eq_ignore_ascii_case("user-agent") {
+            options.push(("user-agent".to_string(), value.clone()));
+        } else if key.eq_ignore_ascii_case("referer") {
+            options.push(("referrer".to_string(), value.clone()));
+            header_fields.push(format!("Referer: {value}"));
+        } else {
+            header_fields.push(format!("{key}: {value}"));
+        }
+    }
+    if !header_fields.is_empty() {
+        options.push(("http-header-fields".to_string(), header_fields.join(",")));
+    }
+    options
+}
+
+/// VLC only accepts a referrer and user agent, and a single external
+/// subtitle (the default one); other headers and tracks are dropped.
+#[derive(Debug)]
+pub struct VlcBackend(String);
+
+impl PlayerBackend for VlcBackend {
+    fn command(&self, request: &LaunchRequest) -> Result<Command> {
+        let mut cmd = build_command(&self.0)?;
+        cmd.arg("--play-and-exit");
+        cmd.arg("--no-one-instance");
+        cmd.arg(format!("--meta-title={}", request.title));
+        if let Some(start) = request.start {
+            cmd.arg(format!("--start-time={start:.1}"));
+        }
+        if request.images {
+            cmd.arg("--image-duration=-1");
+        }
+        for (key, value) in request.headers {
+            if key.eq_ignore_ascii_case("user-agent") {
+                cmd.arg(format!("--http-user-agent={value}"));
+            } else if key.eq_ignore_ascii_case("referer") {
+                cmd.arg(format!("--http-referrer={value}"));
+            }
+        }
+        let sub = request.subtitles.iter().find(|sub| sub.default);
+        if let Some(sub) = sub.or(request.subtitles.first()) {
+            cmd.arg(format!("--sub-file={}", sub.url));
+        }
+        cmd.args(&request.urls);
+        Ok(cmd)
+    }
+}
+
+/// A user-supplied argv template such as
+/// `iina --no-stdin --mpv-force-media-title={title} {url}`.
+///
+/// Placeholders: `{url}`, `{title}`, `{subtitle}`, `{start}`, `{referer}`,
+/// `{user_agent}` and `{header}` ("Name: value"). An argument containing
+/// `{url}`, `{subtitle}` or `{header}` is repeated once per URL, subtitle
+/// track or header; an argument whose placeholder has no value is dropped.
+/// URLs are appended when the template has no `{url}`.
+#[derive(Debug)]
+pub struct TemplateBackend(String);
+
+impl PlayerBackend for TemplateBackend {
+    fn command(&self, request: &LaunchRequest) -> Result<Command> {
+        let parts = shlex::split(&self.0)
+            .filter(|v| !v.is_empty())
+            .ok_or_else(|| anyhow!("Invalid player template: '{}'", self.0))?;
+        let header = |name: &str| {
+            request
+                .headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.clone())
+        };
+        let values = [
+            ("{title}", Some(request.title.to_string())),
+            ("{start}", request.start.map(|s| format!("{s:.1}"))),
+            ("{referer}", header("referer")),
+            ("{user_agent}", header("user-agent")),
+        ];
+        let header_lines: Vec<String> = request
+            .headers
+            .iter()
+            .map(|(key, value)| format!("{key}: {value}"))
+            .collect();
+
+        let mut argv = Vec::new();
+        for part in &parts {
+            let Some(part) = substitute(part, &values) else {
+                continue;
+            };
+            if part.contains("{url}") {
+                argv.extend(request.urls.iter().map(|url| part.replace("{url}", url)));
+            } else if part.contains("{subtitle}") {
+                argv.extend(
+                    request
+                        .subtitles
+                        .iter()
+                        .map(|sub| part.replace("{subtitle}", &sub.url)),
+                );
+            } else if part.contains("{header}") {
+                argv.extend(
+                    header_lines
+                        .iter()
+                        .map(|line| part.replace("{header}", line)),
+                );
+            } else {
+                argv.push(part);
+            }
+        }
+        if !parts.iter().any(|part| part.contains("{url}")) {
+            argv.extend(request.urls.iter().cloned());
+        }
+
+        let (bin, args) = argv
+            .split_first()
+            .ok_or_else(|| anyhow!("Player template is empty"))?;
+        let mut cmd = Command::new(bin);
+        cmd.args(args);
+        Ok(cmd)
+    }
+}
+
+/// Fills the scalar placeholders in `part`; `None` if one of them has no value.
+fn substitute(part: &str, values: &[(&str, Option<String>)]) -> Option<String> {
+    let mut out = part.to_string();
+    for (placeholder, value) in values {
+        if out.contains(placeholder) {
+            out = out.replace(placeholder, value.as_deref()?);
+        }
+    }
+    Some(out)
+}
@@ -1,11 +1,17 @@
-use anyhow::{Context, Result, anyhow, bail};
-use reqwest::Client;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::future::join_all;
+use reqwest::StatusCode;
 use serde::{Deserialize, de::DeserializeOwned};
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
+use tokio::time::timeout;
 
-use super::{AnimeProvider, MangaProvider, USER_AGENT};
+use super::{AnimeProvider, MangaProvider, ProviderError, ProviderResult, USER_AGENT};
+use crate::adapters::{hls::expand_master_playlists, http::HttpClient};
+use crate::config::DEFAULT_SOURCE_PRIORITY;
 use crate::types::{
-    Chapter, ChapterCounts, EpisodeCounts, MangaInfo, Page, ShowInfo, StreamOption, Translation,
+    Chapter, ChapterCounts, EpisodeCounts, MangaInfo, Page, ShowInfo, StreamOption, SubtitleTrack,
+    Translation,
 };
 
 const ALLANIME_API_URL: &str = "https://api.allanime.day/api";
@@ -13,40 +19,50 @@ const ALLANIME_BASE_URL: &str = "https://allanime.day";
 const ALLANIME_REFERER: &str = "https://allmanga.to";
 const ALLANIME_IMAGE_REFERER: &str = "https://allanime.to";
 const ALLANIME_ORIGIN: &str = "https://allanime.day";
-const PREFERRED_PROVIDERS: &[&str] = &["Default", "S-mp4", "Luf-Mp4", "Yt-mp4"];
+const SOURCE_TIMEOUT: Duration = Duration::from_secs(8);
 
 pub struct AllAnimeClient {
-    client: Client,
+    http: HttpClient,
+    source_priority: Vec<String>,
 }
 
 impl AllAnimeClient {
     pub fn new() -> Result<Self> {
-        let client = Client::builder().user_agent(USER_AGENT).build()?;
-        Ok(Self { client })
+        Ok(Self {
+            http: HttpClient::new(USER_AGENT)?,
+            source_priority: DEFAULT_SOURCE_PRIORITY
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+        })
+    }
+
+    /// Overrides the order in which stream sources are listed.
+    pub fn with_source_priority(mut self, priority: Vec<String>) -> Self {
+        if !priority.is_empty() {
+            self.source_priority = priority;
+        }
+        self
     }
 
     /// POST a GraphQL request to the AllAnime API and deserialize the `data` field.
-    async fn post_graphql<T: DeserializeOwned>(&self, body: &serde_json::Value) -> Result<T> {
-        let response = self
-            .client
+    async fn post_graphql<T: DeserializeOwned>(
+        &self,
+        body: &serde_json::Value,
+    ) -> ProviderResult<T> {
+        let request = self
+            .http
             .post(ALLANIME_API_URL)
             .header("Referer", ALLANIME_REFERER)
             .header("Origin", ALLANIME_ORIGIN)
             .header("Accept", "application/json")
-            .json(body)
-            .send()
-            .await?;
-        let status = response.status();
-        let text = response.text().await?;
-        if !status.is_success() {
-            bail!("AllAnime API HTTP {status}: {text}");
-        }
-        let envelope: GraphQlEnvelope<T> =
-            serde_json::from_str(&text).context("failed to parse AllAnime API response")?;
+            .json(body);
+        let text = self.http.send(request).await?.text().await?;
+        let envelope: GraphQlEnvelope<T> = serde_json::from_str(&text)?;
         Self::extract_data(envelope)
     }
 
-    async fn fetch_show_detail(&self, show_id: &str) -> Result<ShowDetail> {
+    async fn fetch_show_detail(&self, show_id: &str) -> ProviderResult<ShowDetail> {
         let body = serde_json::json!({
             "query": SHOW_DETAIL_QUERY,
             "variables": { "showId": show_id }
@@ -60,7 +76,7 @@ impl AllAnimeClient {
         show_id: &str,
         translation: Translation,
         episode: &str,
-    ) -> Result<Vec<SourceDescriptor>> {
+    ) -> ProviderResult<Vec<SourceDescriptor>> {
         let body = serde_json::json!({
             "query": EPISODE_SOURCES_QUERY,
             "variables": {
@@ -69,31 +85,59 @@ impl AllAnimeClient {
                 "episodeString": episode
             }
         });
-        let payload: EpisodePayload = self.post_graphql(&body).await?;
-        Ok(payload.episode.source_urls)
+        // AllAnime answers 400, or a null episode, for episodes that are
+        // listed but not released yet.
+        let payload: EpisodePayload = match self.post_graphql(&body).await {
+            Err(ProviderError::Http(StatusCode::BAD_REQUEST)) => {
+                return Err(ProviderError::EpisodeNotYetAvailable {
+                    episode: episode.to_string(),
+                });
+            }
+            other => other?,
+        };
+        payload
+            .episode
+            .map(|sources| sources.source_urls)
+            .ok_or_else(|| ProviderError::EpisodeNotYetAvailable {
+                episode: episode.to_string(),
+            })
     }
 
-    async fn fetch_clock_json(&self, path: &str) -> Result<ClockResponse> {
+    async fn fetch_clock_json(&self, path: &str) -> ProviderResult<ClockResponse> {
         let url = if path.starts_with("http") {
             path.to_string()
         } else {
             format!("{ALLANIME_BASE_URL}{path}")
         };
-        let response = self
-            .client
+        let request = self
+            .http
             .get(&url)
             .header("Referer", ALLANIME_REFERER)
             .header("Origin", ALLANIME_ORIGIN)
-            .header("Accept", "application/json")
-            .send()
+            .header("Accept", "application/json");
+        Ok(self
+            .http
+            .send(request)
             .await?
-            .error_for_status()?
             .json::<ClockResponse>()
-            .await?;
-        Ok(response)
+            .await?)
+    }
+
+    /// Resolves one episode source into its stream options.
+    async fn fetch_source_streams(
+        &self,
+        source: &SourceDescriptor,
+        path: &str,
+    ) -> ProviderResult<Vec<StreamOption>> {
+        let response = self.fetch_clock_json(path).await?;
+        Ok(response
+            .links
+            .into_iter()
+            .map(|link| build_stream_option(&source.source_name, link))
+            .collect())
     }
 
-    async fn fetch_manga_detail(&self, manga_id: &str) -> Result<MangaDetail> {
+    async fn fetch_manga_detail(&self, manga_id: &str) -> ProviderResult<MangaDetail> {
         let body = serde_json::json!({
             "query": MANGA_DETAIL_QUERY,
             "variables": { "mangaId": manga_id }
@@ -102,18 +146,18 @@ impl AllAnimeClient {
         Ok(payload.manga)
     }
 
-    fn extract_data<T>(envelope: GraphQlEnvelope<T>) -> Result<T> {
+    fn extract_data<T>(envelope: GraphQlEnvelope<T>) -> ProviderResult<T> {
         if let Some(errors) = envelope.errors {
             let joined = errors
                 .into_iter()
                 .map(|e| e.message)
                 .collect::<Vec<_>>()
                 .join("; ");
-            bail!("AllAnime API error: {joined}");
+            return Err(ProviderError::Api(joined));
         }
-        envelope
-            .data
-            .ok_or_else(|| anyhow!("AllAnime API returned empty response"))
+        envelope.data.ok_or_else(|| {
+            ProviderError::ParseChanged(String::from("AllAnime API returned empty response"))
+        })
     }
 }
 
@@ -123,8 +167,13 @@ impl Default for AllAnimeClient {
     }
 }
 
+#[async_trait]
 impl AnimeProvider for AllAnimeClient {
-    async fn search_shows(&self, query: &str, translation: Translation) -> Result<Vec<ShowInfo>> {
+    async fn search_shows(
+        &self,
+        query: &str,
+        translation: Translation,
+    ) -> ProviderResult<Vec<ShowInfo>> {
         let body = serde_json::json!({
             "query": SEARCH_SHOWS_QUERY,
             "variables": {
@@ -155,12 +204,20 @@ impl AnimeProvider for AllAnimeClient {
             .collect())
     }
 
-    async fn fetch_episodes(&self, show_id: &str, translation: Translation) -> Result<Vec<String>> {
+    async fn fetch_episodes(
+        &self,
+        show_id: &str,
+        translation: Translation,
+    ) -> ProviderResult<Vec<String>> {
         let detail = self.fetch_show_detail(show_id).await?;
         let episodes = match translation {
             Translation::Sub => detail.available_episodes_detail.sub,
             Translation::Dub => detail.available_episodes_detail.dub,
-            Translation::Raw => bail!("Raw translation is not supported for anime"),
+            Translation::Raw => {
+                return Err(ProviderError::Unsupported(String::from(
+                    "Raw translation is not supported for anime",
+                )));
+            }
         };
         Ok(episodes)
     }
@@ -170,41 +227,55 @@ impl AnimeProvider for AllAnimeClient {
         show_id: &str,
         translation: Translation,
         episode: &str,
-    ) -> Result<Vec<StreamOption>> {
+    ) -> ProviderResult<Vec<StreamOption>> {
         let sources = self
             .fetch_episode_sources_internal(show_id, translation, episode)
             .await?;
 
-        for provider in PREFERRED_PROVIDERS {
-            if let Some(source) = sources.iter().find(|s| s.source_name == *provider) {
-                let decoded = match decode_provider_path(&source.source_url) {
-                    Some(d) => d,
-                    None => continue,
-                };
-
-                let response = match self.fetch_clock_json(&decoded).await {
-                    Ok(r) => r,
-                    Err(_) => continue,
-                };
-
-                let mut options = Vec::new();
-                for link in response.links {
-                    options.push(build_stream_option(&source.source_name, link));
-                }
-
-                if !options.is_empty() {
-                    options.sort_by(|a, b| b.quality_rank.cmp(&a.quality_rank));
-                    return Ok(options);
-                }
-            }
-        }
-
-        Ok(Vec::new())
+        // Every source is resolved concurrently so a slow or dead one only
+        // costs its own timeout instead of delaying the rest.
+        let results = join_all(
+            sources
+                .iter()
+                .filter_map(|source| {
+                    decode_provider_path(&source.source_url).map(|path| (source, path))
+                })
+                .map(|(source, path)| async move {
+                    let options = timeout(SOURCE_TIMEOUT, self.fetch_source_streams(source, &path))
+                        .await
+                        .ok()
+                        .and_then(Result::ok)
+                        .unwrap_or_default();
+                    // Variant lookups get a budget of their own; when it runs
+                    // out, the master playlists are still playable as they are.
+                    match timeout(SOURCE_TIMEOUT, expand_master_playlists(options.clone())).await {
+                        Ok(expanded) => expanded,
+                        Err(_) => options,
+                    }
+                }),
+        )
+        .await;
+
+        let mut options: Vec<StreamOption> = results.into_iter().flatten().collect();
+        options.sort_by_key(|o| {
+            let priority = self
+                .source_priority
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(&o.provider))
+                .unwrap_or(self.source_priority.len());
+            (priority, std::cmp::Reverse(o.quality_rank))
+        });
+        Ok(options)
     }
 }
 
+#[async_trait]
 impl MangaProvider for AllAnimeClient {
-    async fn search_mangas(&self, query: &str, translation: Translation) -> Result<Vec<MangaInfo>> {
+    async fn search_mangas(
+        &self,
+        query: &str,
+        translation: Translation,
+    ) -> ProviderResult<Vec<MangaInfo>> {
         let body = serde_json::json!({
             "query": SEARCH_MANGAS_QUERY,
             "variables": {
@@ -239,12 +310,16 @@ impl MangaProvider for AllAnimeClient {
         &self,
         manga_id: &str,
         translation: Translation,
-    ) -> Result<Vec<Chapter>> {
+    ) -> ProviderResult<Vec<Chapter>> {
         let detail = self.fetch_manga_detail(manga_id).await?;
         let raw_chapters = match translation {
             Translation::Sub => detail.available_chapters_detail.sub,
             Translation::Raw => detail.available_chapters_detail.raw,
-            Translation::Dub => bail!("Dub translation is not supported for manga"),
+            Translation::Dub => {
+                return Err(ProviderError::Unsupported(String::from(
+                    "Dub translation is not supported for manga",
+                )));
+            }
         };
         Ok(raw_chapters
             .into_iter()
@@ -260,7 +335,7 @@ impl MangaProvider for AllAnimeClient {
         manga_id: &str,
         translation: Translation,
         chapter_id: &str,
-    ) -> Result<Vec<Page>> {
+    ) -> ProviderResult<Vec<Page>> {
         let body = serde_json::json!({
             "query": CHAPTER_PAGES_QUERY,
             "variables": {
@@ -298,11 +373,16 @@ fn build_stream_option(provider: &str, link: ClockLink) -> StreamOption {
         .clone()
         .unwrap_or_else(|| String::from("auto"));
     let quality_rank = quality_rank(&quality_label);
-    let subtitle = link
+    let subtitles = link
         .subtitles
-        .iter()
-        .find(|sub| sub.lang.as_deref() == Some("en") || sub.label.as_deref() == Some("English"))
-        .map(|sub| sub.src.clone());
+        .into_iter()
+        .map(|sub| SubtitleTrack {
+            url: sub.src,
+            lang: sub.lang,
+            label: sub.label,
+            default: false,
+        })
+        .collect();
 
     let mut headers = link.headers;
     if !headers.keys().any(|k| k.eq_ignore_ascii_case("referer")) {
@@ -316,7 +396,7 @@ fn build_stream_option(provider: &str, link: ClockLink) -> StreamOption {
         quality_rank,
         is_hls: link.hls,
         headers,
-        subtitle,
+        subtitles,
     }
 }
 
@@ -491,7 +571,7 @@ struct EpisodeDetail {
 
 #[derive(Debug, Deserialize)]
 struct EpisodePayload {
-    episode: EpisodeSources,
+    episode: Option<EpisodeSources>,
 }
 
 #[derive(Debug, Deserialize)]
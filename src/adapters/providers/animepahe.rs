@@ -0,0 +1,445 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::future::join_all;
+use regex::Regex;
+use reqwest::header;
+use scraper::{Html, Selector};
+use serde::Deserialize;
+
+use super::{AnimeProvider, ProviderError, ProviderResult, USER_AGENT};
+use crate::adapters::http::{HttpClient, client_builder};
+use crate::types::{EpisodeCounts, ShowInfo, StreamOption, Translation};
+
+const ANIMEPAHE_BASE_URL: &str = "https://animepahe.si";
+const DDOS_GUARD_COOKIE_LEN: usize = 16;
+/// DDoS-Guard challenges clients that hit the site in quick bursts.
+const REQUEST_INTERVAL: Duration = Duration::from_millis(250);
+
+static SOURCE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"source\s*=\s*'([^']+)'").expect("valid regex"));
+static PACKED_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\}\('(.*)',\s*(\d+),\s*(\d+),\s*'(.*?)'\.split\('\|'\)").expect("valid regex")
+});
+static WORD_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b\w+\b").expect("valid regex"));
+
+/// AnimePahe: search and episode lists come from its JSON API, streams from
+/// the Kwik embeds listed on each episode's play page.
+pub struct AnimePaheClient {
+    http: HttpClient,
+    /// Episode label -> AnimePahe episode session, per anime session.
+    episode_sessions: Mutex<HashMap<String, HashMap<String, String>>>,
+}
+
+impl AnimePaheClient {
+    pub fn new() -> Result<Self> {
+        // DDoS-Guard lets requests through once they carry any `__ddg2_` cookie.
+        let cookie = format!("__ddg2_={}", random_token(DDOS_GUARD_COOKIE_LEN));
+        let mut headers = header::HeaderMap::new();
+        headers.insert(header::COOKIE, header::HeaderValue::from_str(&cookie)?);
+        headers.insert(
+            header::REFERER,
+            header::HeaderValue::from_static(ANIMEPAHE_BASE_URL),
+        );
+        let client = client_builder(USER_AGENT)
+            .default_headers(headers)
+            .build()?;
+        Ok(Self {
+            http: HttpClient::from_client(client).with_rate_limit(REQUEST_INTERVAL),
+            episode_sessions: Mutex::new(HashMap::new()),
+        })
+    }
+
+    async fn get_text(&self, url: &str) -> ProviderResult<String> {
+        Ok(self.http.send(self.http.get(url)).await?.text().await?)
+    }
+
+    /// Episodes of `anime_session` available in `translation`, as
+    /// (label, episode session) pairs.
+    async fn fetch_release_list(
+        &self,
+        anime_session: &str,
+        translation: Translation,
+    ) -> ProviderResult<Vec<(String, String)>> {
+        let mut episodes = Vec::new();
+        let mut page = 1;
+        loop {
+            let url = format!(
+                "{ANIMEPAHE_BASE_URL}/api?m=release&id={anime_session}&sort=episode_asc&page={page}"
+            );
+            let text = self.get_text(&url).await?;
+            let (page_episodes, last_page) = parse_release_page(&text, translation)?;
+            episodes.extend(page_episodes);
+            if page >= last_page {
+                break;
+            }
+            page += 1;
+        }
+        Ok(episodes)
+    }
+
+    async fn episode_session(&self, anime_session: &str, episode: &str) -> ProviderResult<String> {
+        let cached = self
+            .episode_sessions
+            .lock()
+            .expect("episode session cache poisoned")
+            .get(anime_session)
+            .and_then(|sessions| sessions.get(episode).cloned());
+        if let Some(session) = cached {
+            return Ok(session);
+        }
+        self.fetch_episodes(anime_session, Translation::Sub).await?;
+        self.episode_sessions
+            .lock()
+            .expect("episode session cache poisoned")
+            .get(anime_session)
+            .and_then(|sessions| sessions.get(episode).cloned())
+            .ok_or_else(|| ProviderError::NotFound(format!("Episode {episode}")))
+    }
+
+    /// Resolves a Kwik embed page to its HLS playlist URL.
+    async fn resolve_kwik(&self, embed_url: &str) -> ProviderResult<String> {
+        let request = self
+            .http
+            .get(embed_url)
+            .header(header::REFERER, ANIMEPAHE_BASE_URL);
+        let html = self.http.send(request).await?.text().await?;
+        extract_kwik_source(&html).ok_or_else(|| {
+            ProviderError::ParseChanged(String::from("no stream found in Kwik embed"))
+        })
+    }
+}
+
+impl Default for AnimePaheClient {
+    fn default() -> Self {
+        Self::new().expect("failed to build HTTP client")
+    }
+}
+
+#[async_trait]
+impl AnimeProvider for AnimePaheClient {
+    async fn search_shows(
+        &self,
+        query: &str,
+        _translation: Translation,
+    ) -> ProviderResult<Vec<ShowInfo>> {
+        let url = reqwest::Url::parse_with_params(
+            &format!("{ANIMEPAHE_BASE_URL}/api"),
+            &[("m", "search"), ("q", query)],
+        )
+        .map_err(|err| ProviderError::Unsupported(format!("invalid search query: {err}")))?;
+        let text = self.get_text(url.as_str()).await?;
+        parse_search(&text)
+    }
+
+    async fn fetch_episodes(
+        &self,
+        show_id: &str,
+        translation: Translation,
+    ) -> ProviderResult<Vec<String>> {
+        if translation == Translation::Raw {
+            return Err(ProviderError::Unsupported(String::from(
+                "Raw translation is not supported for anime",
+            )));
+        }
+        let episodes = self.fetch_release_list(show_id, translation).await?;
+        let labels = episodes.iter().map(|(label, _)| label.clone()).collect();
+        self.episode_sessions
+            .lock()
+            .expect("episode session cache poisoned")
+            .insert(show_id.to_string(), episodes.into_iter().collect());
+        Ok(labels)
+    }
+
+    async fn fetch_streams(
+        &self,
+        show_id: &str,
+        translation: Translation,
+        episode: &str,
+    ) -> ProviderResult<Vec<StreamOption>> {
+        let session = self.episode_session(show_id, episode).await?;
+        let html = self
+            .get_text(&format!("{ANIMEPAHE_BASE_URL}/play/{show_id}/{session}"))
+            .await?;
+        let wanted_audio = match translation {
+            Translation::Dub => "eng",
+            _ => "jpn",
+        };
+        let embeds: Vec<KwikEmbed> = parse_play_page(&html)
+            .into_iter()
+            .filter(|embed| embed.audio == wanted_audio)
+            .collect();
+
+        let resolved = join_all(embeds.iter().map(|embed| self.resolve_kwik(&embed.url))).await;
+        let mut streams = Vec::new();
+        for (embed, url) in embeds.into_iter().zip(resolved) {
+            let url = match url {
+                Ok(url) => url,
+                Err(err) => {
+                    eprintln!("Skipping Kwik {}p: {err}", embed.resolution);
+                    continue;
+                }
+            };
+            let referer = origin_of(&embed.url).unwrap_or_else(|| embed.url.clone());
+            let headers = HashMap::from([("Referer".to_string(), referer)]);
+            streams.push(StreamOption {
+                provider: "Kwik".to_string(),
+                url,
+                quality_label: format!("{}p", embed.resolution),
+                quality_rank: embed.resolution as i32,
+                is_hls: true,
+                headers,
+                subtitles: Vec::new(),
+            });
+        }
+        streams.sort_by_key(|o| std::cmp::Reverse(o.quality_rank));
+        Ok(streams)
+    }
+}
+
+/// Search results. The API reports one episode count and no dub count, so
+/// dubbed episodes only show up once the release list is filtered.
+fn parse_search(text: &str) -> ProviderResult<Vec<ShowInfo>> {
+    let search: SearchResponse = serde_json::from_str(text)?;
+    Ok(search
+        .data
+        .into_iter()
+        .map(|anime| ShowInfo {
+            id: anime.session,
+            title: anime.title,
+            available_eps: EpisodeCounts {
+                sub: anime.episodes.unwrap_or(0),
+                dub: 0,
+            },
+        })
+        .collect())
+}
+
+/// One page of the release list: the episodes with audio for `translation`
+/// and the number of the last page. Every episode has the Japanese track;
+/// `audio: "eng"` marks the ones that also have an English dub.
+fn parse_release_page(
+    text: &str,
+    translation: Translation,
+) -> ProviderResult<(Vec<(String, String)>, u32)> {
+    let release: ReleaseResponse = serde_json::from_str(text)?;
+    let episodes = release
+        .data
+        .into_iter()
+        .filter(|ep| translation != Translation::Dub || ep.audio.as_deref() == Some("eng"))
+        .map(|ep| (episode_label(ep.episode), ep.session))
+        .collect();
+    Ok((episodes, release.last_page))
+}
+
+struct KwikEmbed {
+    url: String,
+    resolution: u32,
+    audio: String,
+}
+
+/// Reads the quality buttons (`data-src`, `data-resolution`, `data-audio`)
+/// from an episode's play page.
+fn parse_play_page(html: &str) -> Vec<KwikEmbed> {
+    let doc = Html::parse_document(html);
+    let button_sel = Selector::parse("button[data-src]").expect("valid CSS selector");
+    doc.select(&button_sel)
+        .filter_map(|button| {
+            let attrs = button.value();
+            Some(KwikEmbed {
+                url: attrs.attr("data-src")?.to_string(),
+                resolution: attrs.attr("data-resolution")?.parse().ok()?,
+                audio: attrs.attr("data-audio").unwrap_or("jpn").to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Kwik hides the playlist URL inside a `p,a,c,k,e,d`-packed script.
+fn extract_kwik_source(html: &str) -> Option<String> {
+    if let Some(caps) = SOURCE_RE.captures(html) {
+        return Some(caps[1].to_string());
+    }
+    let unpacked = unpack_packed_js(html)?;
+    SOURCE_RE
+        .captures(&unpacked)
+        .map(|caps| caps[1].to_string())
+}
+
+/// Reverses Dean Edwards' packer: every word token in the payload is an index,
+/// written in base `radix`, into the `|`-separated dictionary.
+fn unpack_packed_js(script: &str) -> Option<String> {
+    let caps = PACKED_RE.captures(script)?;
+    let payload = caps[1].replace("\\'", "'");
+    let radix: u32 = caps[2].parse().ok()?;
+    let words: Vec<&str> = caps[4].split('|').collect();
+
+    Some(
+        WORD_RE
+            .replace_all(&payload, |token: &regex::Captures| {
+                let token = &token[0];
+                decode_base(token, radix)
+                    .and_then(|idx| words.get(idx))
+                    .filter(|word| !word.is_empty())
+                    .map_or_else(|| token.to_string(), |word| word.to_string())
+            })
+            .into_owned(),
+    )
+}
+
+/// Parses `token` in the packer's base (digits, then lowercase, then uppercase).
+fn decode_base(token: &str, radix: u32) -> Option<usize> {
+    token.chars().try_fold(0usize, |acc, ch| {
+        let digit = match ch {
+            '0'..='9' => ch as u32 - '0' as u32,
+            'a'..='z' => ch as u32 - 'a' as u32 + 10,
+            'A'..='Z' => ch as u32 - 'A' as u32 + 36,
+            _ => return None,
+        };
+        (digit < radix).then(|| acc * radix as usize + digit as usize)
+    })
+}
+
+fn origin_of(url: &str) -> Option<String> {
+    let parsed = reqwest::Url::parse(url).ok()?;
+    Some(format!("{}://{}/", parsed.scheme(), parsed.host_str()?))
+}
+
+/// AnimePahe numbers episodes with JSON numbers; `12.0` is shown as `12`.
+fn episode_label(episode: f64) -> String {
+    if episode.fract() == 0.0 {
+        format!("{}", episode as i64)
+    } else {
+        episode.to_string()
+    }
+}
+
+fn random_token(len: usize) -> String {
+    const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    (0..len)
+        .map(|_| CHARSET[rand::random_range(0..CHARSET.len())] as char)
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    data: Vec<SearchEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchEntry {
+    title: String,
+    session: String,
+    #[serde(default)]
+    episodes: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseResponse {
+    #[serde(default)]
+    last_page: u32,
+    #[serde(default)]
+    data: Vec<ReleaseEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseEntry {
+    episode: f64,
+    session: String,
+    /// `jpn`, or `eng` when a dub is available.
+    #[serde(default)]
+    audio: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEARCH: &str = include_str!("fixtures/animepahe/search.json");
+    const RELEASE: &str = include_str!("fixtures/animepahe/release.json");
+    const PLAY: &str = include_str!("fixtures/animepahe/play.html");
+    const KWIK: &str = include_str!("fixtures/animepahe/kwik.html");
+
+    #[test]
+    fn parses_search_results() {
+        let shows = parse_search(SEARCH).unwrap();
+        assert_eq!(shows.len(), 3);
+        assert_eq!(shows[0].id, "a2c3f1d8-3e5b-6c4d-2f9e-7a1b0c9d8e7f");
+        assert_eq!(shows[0].title, "Naruto");
+        assert_eq!(shows[0].available_eps.sub, 220);
+        assert_eq!(shows[1].title, "Naruto: Shippuuden");
+        // Movies come back with `"episodes": null`.
+        assert_eq!(shows[2].available_eps.sub, 0);
+    }
+
+    #[test]
+    fn release_list_keeps_every_episode_for_sub() {
+        let (episodes, last_page) = parse_release_page(RELEASE, Translation::Sub).unwrap();
+        let labels: Vec<&str> = episodes.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels, ["1", "2", "3", "3.5"]);
+        assert_eq!(last_page, 1);
+        assert!(episodes[0].1.starts_with("4b1f0c3a"));
+    }
+
+    #[test]
+    fn release_list_keeps_only_dubbed_episodes_for_dub() {
+        let (episodes, _) = parse_release_page(RELEASE, Translation::Dub).unwrap();
+        let labels: Vec<&str> = episodes.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels, ["1", "2"]);
+    }
+
+    #[test]
+    fn parses_play_page_buttons() {
+        let embeds = parse_play_page(PLAY);
+        // The button with a non-numeric resolution is skipped.
+        assert_eq!(embeds.len(), 4);
+        assert_eq!(embeds[0].url, "https://kwik.si/e/Fa1bYc2dXe3f");
+        assert_eq!(embeds[0].resolution, 360);
+        assert_eq!(embeds[2].resolution, 1080);
+        assert_eq!(embeds[2].audio, "jpn");
+        assert_eq!(embeds[3].audio, "eng");
+    }
+
+    #[test]
+    fn unpacks_kwik_source() {
+        assert!(!KWIK.contains("source='"));
+        assert_eq!(
+            extract_kwik_source(KWIK).as_deref(),
+            Some(
+                "https://eu-11.files.nextcdn.org/stream/11/05/\
+                 4f0e9d8c7b6a5f4e3d2c1b0a9f8e7d6c5b4a3f2e1d0c9b8a7f6e5d4c3b2a1f0e/uwu.m3u8"
+            )
+        );
+    }
+
+    #[test]
+    fn reads_plain_kwik_source() {
+        let html = "<script>const source = 'https://cdn.example/a.m3u8';</script>";
+        assert_eq!(
+            extract_kwik_source(html).as_deref(),
+            Some("https://cdn.example/a.m3u8")
+        );
+        assert_eq!(extract_kwik_source("<html></html>"), None);
+    }
+
+    #[test]
+    fn decodes_packer_base() {
+        assert_eq!(decode_base("0", 62), Some(0));
+        assert_eq!(decode_base("z", 62), Some(35));
+        assert_eq!(decode_base("Z", 62), Some(61));
+        assert_eq!(decode_base("10", 62), Some(62));
+        assert_eq!(decode_base("a", 10), None);
+    }
+
+    #[test]
+    fn labels_whole_and_fractional_episodes() {
+        assert_eq!(episode_label(12.0), "12");
+        assert_eq!(episode_label(3.5), "3.5");
+    }
+}
@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use reqwest::{Response, StatusCode, header::RETRY_AFTER};
+use thiserror::Error;
+
+pub type ProviderResult<T> = std::result::Result<T, ProviderError>;
+
+/// Why a provider call failed, in terms the UI can act on.
+#[derive(Debug, Error)]
+pub enum ProviderError {
+    /// The provider could not be reached (DNS, TLS, reset connection, timeout).
+    #[error("could not reach the provider: {0}")]
+    Network(#[source] reqwest::Error),
+
+    #[error(
+        "rate limited by the provider{}",
+        .retry_after.map(|d| format!(" (retry in {}s)", d.as_secs())).unwrap_or_default()
+    )]
+    RateLimited { retry_after: Option<Duration> },
+
+    /// The provider or something on the network refused the request (401/403/451).
+    #[error("request blocked (HTTP {status}); the site may be blocked on your network")]
+    Blocked { status: StatusCode },
+
+    #[error("{0} not found")]
+    NotFound(String),
+
+    #[error("episode {episode} is not available yet")]
+    EpisodeNotYetAvailable { episode: String },
+
+    /// The response did not have the expected shape; usually the site changed.
+    #[error("unexpected response, the site may have changed: {0}")]
+    ParseChanged(String),
+
+    /// The provider reported an error of its own (API error message).
+    #[error("provider error: {0}")]
+    Api(String),
+
+    #[error("provider returned HTTP {0}")]
+    Http(StatusCode),
+
+    #[error("{0}")]
+    Unsupported(String),
+}
+
+impl ProviderError {
+    pub fn from_status(status: StatusCode, retry_after: Option<Duration>) -> Self {
+        match status {
+            StatusCode::TOO_MANY_REQUESTS => Self::RateLimited { retry_after },
+            StatusCode::UNAUTHORIZED
+            | StatusCode::FORBIDDEN
+            | StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS => Self::Blocked { status },
+            StatusCode::NOT_FOUND => Self::NotFound(String::from("resource")),
+            _ => Self::Http(status),
+        }
+    }
+}
+
+/// Passes successful responses through and turns the rest into a
+/// [`ProviderError`], honouring `Retry-After` on 429s.
+pub fn ensure_success(response: Response) -> ProviderResult<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .map(Duration::from_secs);
+    Err(ProviderError::from_status(status, retry_after))
+}
+
+impl From<reqwest::Error> for ProviderError {
+    fn from(err: reqwest::Error) -> Self {
+        if let Some(status) = err.status() {
+            ProviderError::from_status(status, None)
+        } else if err.is_decode() {
+            ProviderError::ParseChanged(err.to_string())
+        } else {
+            ProviderError::Network(err)
+        }
+    }
+}
+
+impl From<serde_json::Error> for ProviderError {
+    fn from(err: serde_json::Error) -> Self {
+        ProviderError::ParseChanged(err.to_string())
+    }
+}
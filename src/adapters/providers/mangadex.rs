@@ -1,26 +1,28 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 
-use anyhow::{Result, bail};
-use reqwest::Client;
+use anyhow::Result;
+use async_trait::async_trait;
 use serde::Deserialize;
 
-use super::MangaProvider;
+use super::{MangaProvider, ProviderError, ProviderResult};
+use crate::adapters::http::HttpClient;
 use crate::types::{Chapter, ChapterCounts, MangaInfo, Page, Translation};
 
 const MANGADEX_API_URL: &str = "https://api.mangadex.org";
 const CHAPTER_PAGE_LIMIT: usize = 500;
+/// MangaDex allows about five requests per second per client.
+const REQUEST_INTERVAL: Duration = Duration::from_millis(200);
 
 pub struct MangaDexClient {
-    client: Client,
+    http: HttpClient,
 }
 
 impl MangaDexClient {
     pub fn new() -> Result<Self> {
-        let client = Client::builder()
-            .user_agent(concat!("anv/", env!("CARGO_PKG_VERSION")))
-            .build()?;
-        Ok(Self { client })
+        let http = HttpClient::new(concat!("anv/", env!("CARGO_PKG_VERSION")))?
+            .with_rate_limit(REQUEST_INTERVAL);
+        Ok(Self { http })
     }
 
     async fn fetch_manga_feed(
@@ -29,7 +31,7 @@ impl MangaDexClient {
         limit: usize,
         offset: usize,
         languages: &[&str],
-    ) -> Result<MangaFeedResponse> {
+    ) -> ProviderResult<MangaFeedResponse> {
         let mut query = vec![
             ("limit", limit.to_string()),
             ("offset", offset.to_string()),
@@ -40,32 +42,31 @@ impl MangaDexClient {
         }
         let url = format!("{}/manga/{}/feed", MANGADEX_API_URL, manga_id);
 
-        for _attempt in 1..=3u8 {
-            let response = self.client.get(&url).query(&query).send().await?;
-
-            if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
-                let wait = response
-                    .headers()
-                    .get("retry-after")
-                    .and_then(|v| v.to_str().ok())
-                    .and_then(|s| s.parse::<u64>().ok())
-                    .unwrap_or(2);
-                tokio::time::sleep(Duration::from_secs(wait)).await;
-                continue;
-            }
+        let request = self.http.get(&url).query(&query);
+        Ok(self.http.send(request).await?.json().await?)
+    }
 
-            if !response.status().is_success() {
-                let status = response.status();
-                let text = response
-                    .text()
-                    .await
-                    .unwrap_or_else(|_| String::from("<unreadable>"));
-                bail!("MangaDex API error: {} - {}", status, text);
-            }
+    /// Number of distinct numbered chapters in `languages`, counted the way
+    /// `fetch_chapters` lists them.
+    async fn aggregate_count(&self, manga_id: &str, languages: &[&str]) -> ProviderResult<usize> {
+        let url = format!("{}/manga/{}/aggregate", MANGADEX_API_URL, manga_id);
+        let query: Vec<(&str, &str)> = languages
+            .iter()
+            .map(|lang| ("translatedLanguage[]", *lang))
+            .collect();
+        let request = self.http.get(&url).query(&query);
+        let aggregate: AggregateResponse = self.http.send(request).await?.json().await?;
+        Ok(aggregate.chapter_labels().len())
+    }
+}
 
-            return Ok(response.json().await?);
-        }
-        bail!("MangaDex rate limited after 3 attempts");
+fn languages_for(translation: Translation) -> ProviderResult<Vec<&'static str>> {
+    match translation {
+        Translation::Sub => Ok(vec!["en"]),
+        Translation::Raw => Ok(vec!["ja"]),
+        Translation::Dub => Err(ProviderError::Unsupported(String::from(
+            "Dub translation is not supported for manga",
+        ))),
     }
 }
 
@@ -75,30 +76,20 @@ impl Default for MangaDexClient {
     }
 }
 
+#[async_trait]
 impl MangaProvider for MangaDexClient {
     async fn search_mangas(
         &self,
         query: &str,
-        _translation: Translation,
-    ) -> Result<Vec<MangaInfo>> {
+        translation: Translation,
+    ) -> ProviderResult<Vec<MangaInfo>> {
+        languages_for(translation)?;
         let url = format!("{}/manga", MANGADEX_API_URL);
-        let response = self
-            .client
+        let request = self
+            .http
             .get(&url)
-            .query(&[("title", query), ("limit", "25")])
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response
-                .text()
-                .await
-                .unwrap_or_else(|_| String::from("<unreadable>"));
-            bail!("MangaDex API error: {} - {}", status, text);
-        }
-
-        let result: MangaListResponse = response.json().await?;
+            .query(&[("title", query), ("limit", "25")]);
+        let result: MangaListResponse = self.http.send(request).await?.json().await?;
 
         Ok(result
             .data
@@ -121,16 +112,21 @@ impl MangaProvider for MangaDexClient {
             .collect())
     }
 
+    async fn chapter_count(
+        &self,
+        manga_id: &str,
+        translation: Translation,
+    ) -> ProviderResult<usize> {
+        let languages = languages_for(translation)?;
+        self.aggregate_count(manga_id, &languages).await
+    }
+
     async fn fetch_chapters(
         &self,
         manga_id: &str,
         translation: Translation,
-    ) -> Result<Vec<Chapter>> {
-        let languages = match translation {
-            Translation::Sub => vec!["en"],
-            Translation::Raw => vec!["ja"],
-            Translation::Dub => bail!("Dub translation is not supported for manga"),
-        };
+    ) -> ProviderResult<Vec<Chapter>> {
+        let languages = languages_for(translation)?;
 
         let mut chapters: Vec<(String, String)> = Vec::new();
         let mut offset = 0;
@@ -173,19 +169,9 @@ impl MangaProvider for MangaDexClient {
         _manga_id: &str,
         _translation: Translation,
         chapter_id: &str,
-    ) -> Result<Vec<Page>> {
+    ) -> ProviderResult<Vec<Page>> {
         let url = format!("{}/at-home/server/{}", MANGADEX_API_URL, chapter_id);
-        let response = self.client.get(&url).send().await?;
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response
-                .text()
-                .await
-                .unwrap_or_else(|_| String::from("<unreadable>"));
-            bail!("Failed to get at-home server: {} - {}", status, text);
-        }
-
-        let at_home: AtHomeResponse = response.json().await?;
+        let at_home: AtHomeResponse = self.http.send(self.http.get(&url)).await?.json().await?;
 
         let base_url = at_home.base_url;
         let hash = at_home.chapter.hash;
@@ -245,6 +231,32 @@ struct ChapterAttributes {
     chapter: Option<String>,
 }
 
+/// `/manga/{id}/aggregate`: volumes -> chapters. MangaDex serialises an empty
+/// map as `[]`, so both levels are read as loose JSON.
+#[derive(Deserialize)]
+struct AggregateResponse {
+    #[serde(default)]
+    volumes: serde_json::Value,
+}
+
+impl AggregateResponse {
+    fn chapter_labels(&self) -> HashSet<&str> {
+        values_of(&self.volumes)
+            .flat_map(|volume| values_of(&volume["chapters"]))
+            .filter_map(|chapter| chapter["chapter"].as_str())
+            .filter(|label| *label != "none")
+            .collect()
+    }
+}
+
+fn values_of(value: &serde_json::Value) -> Box<dyn Iterator<Item = &serde_json::Value> + '_> {
+    match value {
+        serde_json::Value::Object(map) => Box::new(map.values()),
+        serde_json::Value::Array(items) => Box::new(items.iter()),
+        _ => Box::new(std::iter::empty()),
+    }
+}
+
 #[derive(Deserialize)]
 struct AtHomeResponse {
     #[serde(rename = "baseUrl")]
@@ -1,22 +1,31 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
-use anyhow::{Result, bail};
-use reqwest::Client;
+use anyhow::Result;
+use async_trait::async_trait;
 use scraper::{Html, Selector};
 
-use super::{MangaProvider, USER_AGENT};
+use super::{MangaProvider, ProviderError, ProviderResult, USER_AGENT};
+use crate::adapters::http::HttpClient;
 use crate::types::{Chapter, ChapterCounts, MangaInfo, Page, Translation};
 
 const MANGAPILL_BASE_URL: &str = "https://mangapill.com";
+const REQUEST_INTERVAL: Duration = Duration::from_millis(100);
 
 pub struct MangapillClient {
-    client: Client,
+    http: HttpClient,
 }
 
 impl MangapillClient {
     pub fn new() -> Result<Self> {
-        let client = Client::builder().user_agent(USER_AGENT).build()?;
-        Ok(Self { client })
+        let http = HttpClient::new(USER_AGENT)?.with_rate_limit(REQUEST_INTERVAL);
+        Ok(Self { http })
+    }
+
+    async fn fetch_chapter_list(&self, manga_id: &str) -> ProviderResult<Vec<Chapter>> {
+        // manga_id is like "2085/jujutsu-kaisen"
+        let url = format!("{}/manga/{}", MANGAPILL_BASE_URL, manga_id);
+        let text = self.http.send(self.http.get(&url)).await?.text().await?;
+        Ok(parse_chapter_list(&text))
     }
 }
 
@@ -26,91 +35,37 @@ impl Default for MangapillClient {
     }
 }
 
+#[async_trait]
 impl MangaProvider for MangapillClient {
     async fn search_mangas(
         &self,
         query: &str,
         _translation: Translation,
-    ) -> Result<Vec<MangaInfo>> {
+    ) -> ProviderResult<Vec<MangaInfo>> {
         let url = format!("{}/search", MANGAPILL_BASE_URL);
-        let response = self.client.get(&url).query(&[("q", query)]).send().await?;
-
-        if !response.status().is_success() {
-            bail!("Mangapill error: {}", response.status());
-        }
-
-        let text = response.text().await?;
-        let doc = Html::parse_document(&text);
-
-        let link_sel = Selector::parse(r#"a[href^="/manga/"]"#).expect("valid CSS selector");
-        let title_sel = Selector::parse("div.font-black, div.mt-3").expect("valid CSS selector");
-
-        let mut mangas = Vec::new();
-        for link in doc.select(&link_sel) {
-            let href = link.value().attr("href").unwrap_or_default();
-            let trimmed = href.trim_start_matches("/manga/");
-            if trimmed.is_empty() {
-                continue;
-            }
-            let title = link
-                .select(&title_sel)
-                .next()
-                .map(|el| el.text().collect::<String>())
-                .unwrap_or_default();
-            let title = title.trim().to_string();
-            if title.is_empty() {
-                continue;
-            }
-            mangas.push(MangaInfo {
-                id: trimmed.to_string(),
-                title,
-                available_chapters: ChapterCounts::default(),
-            });
-        }
-
-        Ok(mangas)
+        let request = self.http.get(&url).query(&[("q", query)]);
+        let text = self.http.send(request).await?.text().await?;
+        Ok(parse_search_results(&text))
     }
 
     async fn fetch_chapters(
         &self,
         manga_id: &str,
         _translation: Translation,
-    ) -> Result<Vec<Chapter>> {
-        // manga_id is like "2085/jujutsu-kaisen"
-        let url = format!("{}/manga/{}", MANGAPILL_BASE_URL, manga_id);
-        let response = self.client.get(&url).send().await?;
-
-        if !response.status().is_success() {
-            bail!("Mangapill error: {}", response.status());
-        }
-
-        let text = response.text().await?;
-        let doc = Html::parse_document(&text);
-
-        let sel = Selector::parse(r#"a[href^="/chapters/"]"#).expect("valid CSS selector");
-
-        let mut chapters: Vec<Chapter> = Vec::new();
-        for el in doc.select(&sel) {
-            let href = el.value().attr("href").unwrap_or_default();
-            let slug = href.trim_start_matches("/chapters/").to_string();
-            let text: String = el.text().collect();
-            let label = text.replace("Chapter ", "").trim().to_string();
-            if label.is_empty() || slug.is_empty() {
-                continue;
-            }
-            chapters.push(Chapter { id: slug, label });
-        }
-
-        chapters.sort_by(|a, b| {
-            let a_num = a.label.parse::<f64>().unwrap_or(0.0);
-            let b_num = b.label.parse::<f64>().unwrap_or(0.0);
-            a_num
-                .partial_cmp(&b_num)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-        chapters.dedup_by(|a, b| a.label == b.label);
+    ) -> ProviderResult<Vec<Chapter>> {
+        self.fetch_chapter_list(manga_id).await
+    }
 
-        Ok(chapters)
+    /// Search results carry no chapter counts and the chapter list is a whole
+    /// manga page, too much to fetch for every hit, so none is reported.
+    async fn chapter_count(
+        &self,
+        _manga_id: &str,
+        _translation: Translation,
+    ) -> ProviderResult<usize> {
+        Err(ProviderError::Unsupported(String::from(
+            "Mangapill does not report chapter counts",
+        )))
     }
 
     async fn fetch_pages(
@@ -118,15 +73,9 @@ impl MangaProvider for MangapillClient {
         _manga_id: &str,
         _translation: Translation,
         chapter_id: &str,
-    ) -> Result<Vec<Page>> {
+    ) -> ProviderResult<Vec<Page>> {
         let url = format!("{}/chapters/{}", MANGAPILL_BASE_URL, chapter_id);
-        let response = self.client.get(&url).send().await?;
-
-        if !response.status().is_success() {
-            bail!("Mangapill chapter error: {}", response.status());
-        }
-
-        let text = response.text().await?;
+        let text = self.http.send(self.http.get(&url)).await?.text().await?;
         let doc = Html::parse_document(&text);
 
         let sel = Selector::parse("img.js-page[data-src]").expect("valid CSS selector");
@@ -143,3 +92,89 @@ impl MangaProvider for MangapillClient {
         Ok(pages)
     }
 }
+
+fn parse_search_results(html: &str) -> Vec<MangaInfo> {
+    let doc = Html::parse_document(html);
+
+    let link_sel = Selector::parse(r#"a[href^="/manga/"]"#).expect("valid CSS selector");
+    let title_sel = Selector::parse("div.font-black, div.mt-3").expect("valid CSS selector");
+
+    let mut mangas = Vec::new();
+    for link in doc.select(&link_sel) {
+        let href = link.value().attr("href").unwrap_or_default();
+        let trimmed = href.trim_start_matches("/manga/");
+        if trimmed.is_empty() {
+            continue;
+        }
+        let title = link
+            .select(&title_sel)
+            .next()
+            .map(|el| el.text().collect::<String>())
+            .unwrap_or_default();
+        let title = title.trim().to_string();
+        if title.is_empty() {
+            continue;
+        }
+        mangas.push(MangaInfo {
+            id: trimmed.to_string(),
+            title,
+            available_chapters: ChapterCounts::default(),
+        });
+    }
+    mangas
+}
+
+fn parse_chapter_list(html: &str) -> Vec<Chapter> {
+    let doc = Html::parse_document(html);
+    let sel = Selector::parse(r#"a[href^="/chapters/"]"#).expect("valid CSS selector");
+
+    let mut chapters: Vec<Chapter> = Vec::new();
+    for el in doc.select(&sel) {
+        let href = el.value().attr("href").unwrap_or_default();
+        let slug = href.trim_start_matches("/chapters/").to_string();
+        let text: String = el.text().collect();
+        let label = text.replace("Chapter ", "").trim().to_string();
+        if label.is_empty() || slug.is_empty() {
+            continue;
+        }
+        chapters.push(Chapter { id: slug, label });
+    }
+
+    chapters.sort_by(|a, b| {
+        let a_num = a.label.parse::<f64>().unwrap_or(0.0);
+        let b_num = b.label.parse::<f64>().unwrap_or(0.0);
+        a_num
+            .partial_cmp(&b_num)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    chapters.dedup_by(|a, b| a.label == b.label);
+    chapters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEARCH: &str = include_str!("fixtures/mangapill/search.html");
+    const MANGA: &str = include_str!("fixtures/mangapill/manga.html");
+
+    #[test]
+    fn parses_search_results() {
+        let mangas = parse_search_results(SEARCH);
+        // The cover links have no title and are skipped.
+        assert_eq!(mangas.len(), 2);
+        assert_eq!(mangas[0].id, "2085/jujutsu-kaisen");
+        assert_eq!(mangas[0].title, "Jujutsu Kaisen");
+        assert_eq!(mangas[1].id, "5232/jujutsu-kaisen-0");
+        assert_eq!(mangas[1].title, "Jujutsu Kaisen 0");
+    }
+
+    #[test]
+    fn chapter_list_is_sorted_and_deduplicated() {
+        let chapters = parse_chapter_list(MANGA);
+        let labels: Vec<&str> = chapters.iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(labels, ["1", "2", "10", "10.5", "11"]);
+        assert_eq!(chapters[0].id, "2085-10001000/jujutsu-kaisen-chapter-1");
+        assert_eq!(chapters[3].id, "2085-10010500/jujutsu-kaisen-chapter-10.5");
+    }
+}
@@ -0,0 +1,275 @@
+use std::{
+    fs,
+    future::Future,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dirs_next::cache_dir;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use super::{AnimeProvider, MangaProvider, ProviderResult};
+use crate::{
+    adapters::reader::cache::sanitize_cache_segment,
+    types::{
+        Chapter, MangaInfo, Page, Provider, ShowInfo, StreamOption, Translation, normalize_title,
+    },
+};
+
+const SEARCH_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+/// Episode and chapter lists grow while a series airs, so they go stale sooner.
+const LIST_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// Set by `--refresh`: skip cached entries (fresh or stale) and fetch again.
+static BYPASS: AtomicBool = AtomicBool::new(false);
+
+pub fn set_refresh(refresh: bool) {
+    BYPASS.store(refresh, Ordering::Relaxed);
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry<T> {
+    fetched_at: DateTime<Utc>,
+    value: T,
+}
+
+impl<T> Entry<T> {
+    fn is_fresh(&self, ttl: Duration) -> bool {
+        (Utc::now() - self.fetched_at)
+            .to_std()
+            .is_ok_and(|age| age < ttl)
+    }
+}
+
+/// Where one cached response lives:
+/// `<cache>/anv/metadata/<provider>/<kind>-<translation>-<key>.json`.
+struct CacheKey {
+    path: Option<PathBuf>,
+}
+
+impl CacheKey {
+    fn new(provider: Provider, kind: &str, translation: Translation, key: &str) -> Self {
+        let path = cache_dir().map(|dir| {
+            dir.join("anv")
+                .join("metadata")
+                .join(provider.as_str())
+                .join(format!(
+                    "{kind}-{}-{}.json",
+                    translation.as_str(),
+                    sanitize_cache_segment(key)
+                ))
+        });
+        Self { path }
+    }
+
+    fn read<T: DeserializeOwned>(&self) -> Option<Entry<T>> {
+        let text = fs::read_to_string(self.path.as_ref()?).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    fn write<T: Serialize>(&self, value: &T) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let entry = Entry {
+            fetched_at: Utc::now(),
+            value,
+        };
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| {
+                let json = serde_json::to_string(&entry).map_err(std::io::Error::other)?;
+                fs::write(path, json)
+            });
+        if let Err(err) = written {
+            eprintln!("Warning: failed to write metadata cache: {err}");
+        }
+    }
+}
+
+/// Serves `key` from disk while it is younger than `ttl`. Older entries are
+/// still returned straight away while `fetch` refreshes them in the
+/// background, and are the fallback when `fetch` fails (offline use).
+async fn cached<T, F, Fut>(key: CacheKey, ttl: Duration, fetch: F) -> ProviderResult<Vec<T>>
+where
+    T: Serialize + DeserializeOwned + Send + 'static,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = ProviderResult<Vec<T>>> + Send + 'static,
+{
+    if !BYPASS.load(Ordering::Relaxed) {
+        if let Some(entry) = key.read::<Vec<T>>() {
+            if !entry.is_fresh(ttl) {
+                let refresh = fetch();
+                tokio::spawn(async move {
+                    if let Ok(value) = refresh.await {
+                        if !value.is_empty() {
+                            key.write(&value);
+                        }
+                    }
+                });
+            }
+            return Ok(entry.value);
+        }
+    }
+
+    match fetch().await {
+        Ok(value) => {
+            // An empty list is more often a hiccup than the truth; don't pin it.
+            if !value.is_empty() {
+                key.write(&value);
+            }
+            Ok(value)
+        }
+        Err(err) => match key.read::<Vec<T>>() {
+            Some(entry) => {
+                eprintln!(
+                    "Using cached data from {} ({err}).",
+                    entry.fetched_at.format("%Y-%m-%d %H:%M")
+                );
+                Ok(entry.value)
+            }
+            None => Err(err),
+        },
+    }
+}
+
+/// Caches search results and episode lists; stream URLs expire and are
+/// always fetched.
+pub struct CachedAnimeProvider {
+    provider: Provider,
+    inner: Arc<dyn AnimeProvider>,
+}
+
+impl CachedAnimeProvider {
+    pub fn new(provider: Provider, inner: Box<dyn AnimeProvider>) -> Self {
+        Self {
+            provider,
+            inner: Arc::from(inner),
+        }
+    }
+}
+
+#[async_trait]
+impl AnimeProvider for CachedAnimeProvider {
+    async fn search_shows(
+        &self,
+        query: &str,
+        translation: Translation,
+    ) -> ProviderResult<Vec<ShowInfo>> {
+        let key = CacheKey::new(
+            self.provider,
+            "search",
+            translation,
+            &normalize_title(query),
+        );
+        let inner = Arc::clone(&self.inner);
+        let query = query.to_string();
+        cached(key, SEARCH_TTL, move || async move {
+            inner.search_shows(&query, translation).await
+        })
+        .await
+    }
+
+    async fn fetch_episodes(
+        &self,
+        show_id: &str,
+        translation: Translation,
+    ) -> ProviderResult<Vec<String>> {
+        let key = CacheKey::new(self.provider, "episodes", translation, show_id);
+        let inner = Arc::clone(&self.inner);
+        let show_id = show_id.to_string();
+        cached(key, LIST_TTL, move || async move {
+            inner.fetch_episodes(&show_id, translation).await
+        })
+        .await
+    }
+
+    async fn fetch_streams(
+        &self,
+        show_id: &str,
+        translation: Translation,
+        episode: &str,
+    ) -> ProviderResult<Vec<StreamOption>> {
+        self.inner
+            .fetch_streams(show_id, translation, episode)
+            .await
+    }
+}
+
+/// Caches search results and chapter lists; page URLs expire and are always
+/// fetched.
+pub struct CachedMangaProvider {
+    provider: Provider,
+    inner: Arc<dyn MangaProvider>,
+}
+
+impl CachedMangaProvider {
+    pub fn new(provider: Provider, inner: Box<dyn MangaProvider>) -> Self {
+        Self {
+            provider,
+            inner: Arc::from(inner),
+        }
+    }
+}
+
+#[async_trait]
+impl MangaProvider for CachedMangaProvider {
+    async fn search_mangas(
+        &self,
+        query: &str,
+        translation: Translation,
+    ) -> ProviderResult<Vec<MangaInfo>> {
+        let key = CacheKey::new(
+            self.provider,
+            "search",
+            translation,
+            &normalize_title(query),
+        );
+        let inner = Arc::clone(&self.inner);
+        let query = query.to_string();
+        cached(key, SEARCH_TTL, move || async move {
+            inner.search_mangas(&query, translation).await
+        })
+        .await
+    }
+
+    async fn fetch_chapters(
+        &self,
+        manga_id: &str,
+        translation: Translation,
+    ) -> ProviderResult<Vec<Chapter>> {
+        let key = CacheKey::new(self.provider, "chapters", translation, manga_id);
+        let inner = Arc::clone(&self.inner);
+        let manga_id = manga_id.to_string();
+        cached(key, LIST_TTL, move || async move {
+            inner.fetch_chapters(&manga_id, translation).await
+        })
+        .await
+    }
+
+    async fn chapter_count(
+        &self,
+        manga_id: &str,
+        translation: Translation,
+    ) -> ProviderResult<usize> {
+        self.inner.chapter_count(manga_id, translation).await
+    }
+
+    async fn fetch_pages(
+        &self,
+        manga_id: &str,
+        translation: Translation,
+        chapter_id: &str,
+    ) -> ProviderResult<Vec<Page>> {
+        self.inner
+            .fetch_pages(manga_id, translation, chapter_id)
+            .await
+    }
+}
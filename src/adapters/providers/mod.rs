@@ -1,8 +1,13 @@
 pub mod allanime;
+pub mod animepahe;
+pub mod error;
 pub mod mangadex;
 pub mod mangapill;
+pub mod metadata_cache;
 pub mod ports;
+pub mod registry;
 
+pub use error::{ProviderError, ProviderResult};
 pub use ports::{AnimeProvider, MangaProvider};
 
 pub const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/121.0 Safari/537.36";
@@ -1,31 +1,54 @@
-use anyhow::Result;
+use async_trait::async_trait;
 
+use super::ProviderResult;
 use crate::types::{Chapter, MangaInfo, Page, ShowInfo, StreamOption, Translation};
 
-#[allow(async_fn_in_trait)]
-pub trait AnimeProvider {
-    async fn search_shows(&self, query: &str, translation: Translation) -> Result<Vec<ShowInfo>>;
-    async fn fetch_episodes(&self, show_id: &str, translation: Translation) -> Result<Vec<String>>;
+#[async_trait]
+pub trait AnimeProvider: Send + Sync {
+    async fn search_shows(
+        &self,
+        query: &str,
+        translation: Translation,
+    ) -> ProviderResult<Vec<ShowInfo>>;
+    async fn fetch_episodes(
+        &self,
+        show_id: &str,
+        translation: Translation,
+    ) -> ProviderResult<Vec<String>>;
     async fn fetch_streams(
         &self,
         show_id: &str,
         translation: Translation,
         episode: &str,
-    ) -> Result<Vec<StreamOption>>;
+    ) -> ProviderResult<Vec<StreamOption>>;
 }
 
-#[allow(async_fn_in_trait)]
-pub trait MangaProvider {
-    async fn search_mangas(&self, query: &str, translation: Translation) -> Result<Vec<MangaInfo>>;
+#[async_trait]
+pub trait MangaProvider: Send + Sync {
+    async fn search_mangas(
+        &self,
+        query: &str,
+        translation: Translation,
+    ) -> ProviderResult<Vec<MangaInfo>>;
     async fn fetch_chapters(
         &self,
         manga_id: &str,
         translation: Translation,
-    ) -> Result<Vec<Chapter>>;
+    ) -> ProviderResult<Vec<Chapter>>;
+    /// Number of chapters `fetch_chapters` would list. Searches may leave
+    /// [`MangaInfo::available_chapters`] at zero when the provider does not
+    /// report counts; this looks one up for a single title.
+    async fn chapter_count(
+        &self,
+        manga_id: &str,
+        translation: Translation,
+    ) -> ProviderResult<usize> {
+        Ok(self.fetch_chapters(manga_id, translation).await?.len())
+    }
     async fn fetch_pages(
         &self,
         manga_id: &str,
         translation: Translation,
         chapter_id: &str,
-    ) -> Result<Vec<Page>>;
+    ) -> ProviderResult<Vec<Page>>;
 }
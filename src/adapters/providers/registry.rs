@@ -0,0 +1,171 @@
+use anyhow::{Result, bail};
+
+use super::{
+    AnimeProvider, MangaProvider,
+    allanime::AllAnimeClient,
+    animepahe::AnimePaheClient,
+    mangadex::MangaDexClient,
+    mangapill::MangapillClient,
+    metadata_cache::{CachedAnimeProvider, CachedMangaProvider},
+};
+use crate::{
+    config::StreamsConfig,
+    types::{Provider, Translation},
+};
+
+/// What a provider can serve.
+#[derive(Debug, Clone, Copy)]
+pub struct Capabilities {
+    pub anime: bool,
+    pub manga: bool,
+    pub sub: bool,
+    pub dub: bool,
+    pub raw: bool,
+}
+
+impl Capabilities {
+    pub fn supports(&self, translation: Translation) -> bool {
+        match translation {
+            Translation::Sub => self.sub,
+            Translation::Dub => self.dub,
+            Translation::Raw => self.raw,
+        }
+    }
+
+    /// Short human-readable summary, e.g. "anime, manga (sub, dub, raw)".
+    pub fn summary(&self) -> String {
+        let media: Vec<&str> = [("anime", self.anime), ("manga", self.manga)]
+            .into_iter()
+            .filter_map(|(name, on)| on.then_some(name))
+            .collect();
+        let translations: Vec<&str> = [("sub", self.sub), ("dub", self.dub), ("raw", self.raw)]
+            .into_iter()
+            .filter_map(|(name, on)| on.then_some(name))
+            .collect();
+        format!("{} ({})", media.join(", "), translations.join(", "))
+    }
+}
+
+type AnimeFactory = fn(&StreamsConfig) -> Result<Box<dyn AnimeProvider>>;
+type MangaFactory = fn() -> Result<Box<dyn MangaProvider>>;
+
+/// A registered provider: its name, what it supports and how to build its clients.
+pub struct ProviderDescriptor {
+    pub provider: Provider,
+    pub name: &'static str,
+    pub capabilities: Capabilities,
+    anime: Option<AnimeFactory>,
+    manga: Option<MangaFactory>,
+}
+
+impl ProviderDescriptor {
+    /// Builds the anime client, behind the on-disk metadata cache.
+    pub fn anime_client(&self, streams: &StreamsConfig) -> Result<Box<dyn AnimeProvider>> {
+        match self.anime {
+            Some(build) => Ok(Box::new(CachedAnimeProvider::new(
+                self.provider,
+                build(streams)?,
+            ))),
+            None => bail!("Provider '{}' does not support anime", self.name),
+        }
+    }
+
+    /// Builds the manga client, behind the on-disk metadata cache.
+    pub fn manga_client(&self) -> Result<Box<dyn MangaProvider>> {
+        match self.manga {
+            Some(build) => Ok(Box::new(CachedMangaProvider::new(self.provider, build()?))),
+            None => bail!("Provider '{}' does not support manga", self.name),
+        }
+    }
+}
+
+/// Every provider anv knows about, in the order they are listed to users.
+pub static PROVIDERS: &[ProviderDescriptor] = &[
+    ProviderDescriptor {
+        provider: Provider::Allanime,
+        name: "allanime",
+        capabilities: Capabilities {
+            anime: true,
+            manga: true,
+            sub: true,
+            dub: true,
+            raw: true,
+        },
+        anime: Some(|streams| {
+            Ok(Box::new(
+                AllAnimeClient::new()?.with_source_priority(streams.source_priority.clone()),
+            ))
+        }),
+        manga: Some(|| Ok(Box::new(AllAnimeClient::new()?))),
+    },
+    ProviderDescriptor {
+        provider: Provider::Animepahe,
+        name: "animepahe",
+        capabilities: Capabilities {
+            anime: true,
+            manga: false,
+            sub: true,
+            dub: true,
+            raw: false,
+        },
+        anime: Some(|_| Ok(Box::new(AnimePaheClient::new()?))),
+        manga: None,
+    },
+    ProviderDescriptor {
+        provider: Provider::Mangadex,
+        name: "mangadex",
+        capabilities: Capabilities {
+            anime: false,
+            manga: true,
+            sub: true,
+            dub: false,
+            raw: true,
+        },
+        anime: None,
+        manga: Some(|| Ok(Box::new(MangaDexClient::new()?))),
+    },
+    ProviderDescriptor {
+        provider: Provider::Mangapill,
+        name: "mangapill",
+        capabilities: Capabilities {
+            anime: false,
+            manga: true,
+            sub: true,
+            dub: false,
+            raw: false,
+        },
+        anime: None,
+        manga: Some(|| Ok(Box::new(MangapillClient::new()?))),
+    },
+];
+
+pub fn descriptor(provider: Provider) -> &'static ProviderDescriptor {
+    PROVIDERS
+        .iter()
+        .find(|d| d.provider == provider)
+        .expect("every Provider variant is registered")
+}
+
+pub fn find(name: &str) -> Option<&'static ProviderDescriptor> {
+    PROVIDERS
+        .iter()
+        .find(|d| d.name.eq_ignore_ascii_case(name.trim()))
+}
+
+/// Provider to use for anime: `requested` if it supports anime, otherwise the
+/// first registered anime provider (with a warning).
+pub fn anime_provider(requested: Provider) -> &'static ProviderDescriptor {
+    let wanted = descriptor(requested);
+    if wanted.capabilities.anime {
+        return wanted;
+    }
+    let fallback = PROVIDERS
+        .iter()
+        .find(|d| d.capabilities.anime)
+        .expect("at least one anime provider is registered");
+    eprintln!(
+        "Warning: '{}' does not support anime. Switching to '{}'.",
+        wanted.name, fallback.name
+    );
+    fallback
+}
@@ -1,19 +1,47 @@
 use crate::{
-    adapters::providers::USER_AGENT,
+    adapters::{
+        http::{CONNECT_TIMEOUT, HttpClient, READ_TIMEOUT, client_builder},
+        providers::{ProviderError, USER_AGENT},
+    },
     types::{Page, Translation},
 };
 
 use anyhow::{Context, Result, anyhow, bail};
 use dirs_next::cache_dir;
-use reqwest::Client;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     fs,
     path::{Path, PathBuf},
-    process::Command,
+    process::{Command, Stdio},
 };
 
 pub const CACHE_ACCEPT: &str = "image/avif,image/webp,image/*,*/*;q=0.8";
+/// Written into each cached chapter directory so the offline library can tell
+/// a fully cached chapter from one that was only partly downloaded.
+const CHAPTER_INFO_FILE: &str = ".chapter.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedChapterInfo {
+    pub page_count: usize,
+}
+
+impl CachedChapterInfo {
+    pub fn read(chapter_dir: &Path) -> Option<Self> {
+        let text = fs::read_to_string(chapter_dir.join(CHAPTER_INFO_FILE)).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    fn write(&self, chapter_dir: &Path) -> Result<()> {
+        if Self::read(chapter_dir).as_ref() == Some(self) {
+            return Ok(());
+        }
+        let path = chapter_dir.join(CHAPTER_INFO_FILE);
+        let json = serde_json::to_string(self)?;
+        fs::write(&path, json).with_context(|| format!("failed to write {}", path.display()))
+    }
+}
 
 pub struct MangaCacheState {
     pub cached_pages: Vec<Option<PathBuf>>,
@@ -21,12 +49,12 @@ pub struct MangaCacheState {
     pub cdn_blocked: bool,
 }
 
-pub fn build_cache_http_client() -> Result<Client> {
-    Client::builder()
-        .user_agent(USER_AGENT)
+pub fn build_cache_http_client() -> Result<HttpClient> {
+    let client = client_builder(USER_AGENT)
         .redirect(reqwest::redirect::Policy::none())
         .build()
-        .context("failed to create cache HTTP client")
+        .context("failed to create cache HTTP client")?;
+    Ok(HttpClient::from_client(client))
 }
 
 pub async fn cache_manga_pages(
@@ -40,6 +68,10 @@ pub async fn cache_manga_pages(
     let chapter_dir = manga_cache_chapter_dir(manga_id, translation, chapter, cache_base_override)?;
     fs::create_dir_all(&chapter_dir)
         .with_context(|| format!("failed to create cache directory {}", chapter_dir.display()))?;
+    CachedChapterInfo {
+        page_count: pages.len(),
+    }
+    .write(&chapter_dir)?;
 
     let preload_target = preload_count.min(pages.len());
     let cache_files: Vec<PathBuf> = pages
@@ -66,11 +98,9 @@ pub async fn cache_manga_pages(
         match download_page(&http, page, file).await {
             Ok(()) => cached[idx] = Some(file.clone()),
             Err(err) => {
-                let msg = err.to_string();
-                if msg.contains("403") || msg.contains("Forbidden") {
+                if is_blocked(&err) {
                     eprintln!(
-                        "Image CDN returned 403 \u{2014} this domain is blocked on your network.\n\
-                         Try a different provider: --provider mangadex  or  --provider mangapill"
+                        "Image CDN returned 403 \u{2014} this domain is blocked on your network."
                     );
                     return Ok(MangaCacheState {
                         cached_pages: cached,
@@ -110,8 +140,7 @@ pub async fn cache_manga_pages(
                     continue;
                 }
                 if let Err(err) = download_page_curl(&page, &file) {
-                    let msg = err.to_string();
-                    if msg.contains("403") || msg.contains("exit status: 22") {
+                    if is_blocked(&err) {
                         break;
                     }
                     eprintln!("Background cache miss for {}: {}", page.url, err);
@@ -127,7 +156,7 @@ pub async fn cache_manga_pages(
     })
 }
 
-pub async fn download_page(http: &Client, page: &Page, file: &Path) -> Result<()> {
+pub async fn download_page(http: &HttpClient, page: &Page, file: &Path) -> Result<()> {
     match download_page_reqwest(http, page, file).await {
         Ok(()) => Ok(()),
         Err(primary_err) => download_page_curl(page, file)
@@ -135,7 +164,7 @@ pub async fn download_page(http: &Client, page: &Page, file: &Path) -> Result<()
     }
 }
 
-async fn download_page_reqwest(http: &Client, page: &Page, file: &Path) -> Result<()> {
+async fn download_page_reqwest(http: &HttpClient, page: &Page, file: &Path) -> Result<()> {
     let bytes = fetch_with_headers(http, &page.url, &page.headers).await?;
     fs::write(file, &bytes)
         .with_context(|| format!("failed to write cached page {}", file.display()))?;
@@ -143,7 +172,7 @@ async fn download_page_reqwest(http: &Client, page: &Page, file: &Path) -> Resul
 }
 
 async fn fetch_with_headers(
-    http: &Client,
+    http: &HttpClient,
     url: &str,
     headers: &HashMap<String, String>,
 ) -> Result<Vec<u8>> {
@@ -155,8 +184,8 @@ async fn fetch_with_headers(
         req
     };
 
-    let resp = build_req(url)
-        .send()
+    let resp = http
+        .send(build_req(url))
         .await
         .with_context(|| format!("request failed for {url}"))?;
 
@@ -168,18 +197,13 @@ async fn fetch_with_headers(
             .and_then(|v| v.to_str().ok())
             .map(|s| s.to_string())
             .ok_or_else(|| anyhow!("redirect with no Location header"))?;
-        build_req(&location)
-            .send()
+        http.send(build_req(&location))
             .await
             .with_context(|| format!("request failed after redirect to {location}"))?
     } else {
         resp
     };
 
-    let status = resp.status();
-    if !status.is_success() {
-        bail!("HTTP {status}");
-    }
     resp.bytes()
         .await
         .map(|b| b.to_vec())
@@ -193,6 +217,12 @@ pub fn download_page_curl(page: &Page, file: &Path) -> Result<()> {
         .arg("--silent")
         .arg("--show-error")
         .arg("--location-trusted")
+        .arg("--connect-timeout")
+        .arg(CONNECT_TIMEOUT.as_secs().to_string())
+        .arg("--speed-time")
+        .arg(READ_TIMEOUT.as_secs().to_string())
+        .arg("--speed-limit")
+        .arg("1")
         .arg("--user-agent")
         .arg(USER_AGENT)
         .arg("--header")
@@ -200,31 +230,60 @@ pub fn download_page_curl(page: &Page, file: &Path) -> Result<()> {
     for (key, value) in &page.headers {
         cmd.arg("--header").arg(format!("{key}: {value}"));
     }
-    cmd.arg("--output").arg(file).arg(&page.url);
-    let status = cmd
-        .status()
+    cmd.arg("--write-out")
+        .arg("%{http_code}")
+        .arg("--output")
+        .arg(file)
+        .arg(&page.url)
+        .stderr(Stdio::inherit());
+    let output = cmd
+        .output()
         .context("failed to run curl for cache download")?;
-    if !status.success() {
-        bail!("curl exited with status {status}");
+    if !output.status.success() {
+        // With --fail, curl still writes the final status code to stdout.
+        let status = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse::<u16>()
+            .ok()
+            .and_then(|code| StatusCode::from_u16(code).ok())
+            .filter(|status| status.is_client_error() || status.is_server_error());
+        if let Some(status) = status {
+            return Err(ProviderError::from_status(status, None).into());
+        }
+        bail!("curl exited with status {}", output.status);
     }
     Ok(())
 }
 
+/// The image CDN refused the request, as opposed to a flaky download.
+fn is_blocked(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<ProviderError>(),
+        Some(ProviderError::Blocked { .. })
+    )
+}
+
+/// `<cache>/anv/manga-pages`, holding one directory per manga id.
+pub fn manga_cache_root(cache_base_override: Option<&Path>) -> Result<PathBuf> {
+    let base = if let Some(path) = cache_base_override {
+        path.to_path_buf()
+    } else {
+        cache_dir().ok_or_else(|| anyhow!("Could not determine cache directory"))?
+    };
+    Ok(base.join("anv").join("manga-pages"))
+}
+
+pub fn manga_cache_dir(manga_id: &str, cache_base_override: Option<&Path>) -> Result<PathBuf> {
+    Ok(manga_cache_root(cache_base_override)?.join(sanitize_cache_segment(manga_id)))
+}
+
 pub fn manga_cache_chapter_dir(
     manga_id: &str,
     translation: Translation,
     chapter: &str,
     cache_base_override: Option<&Path>,
 ) -> Result<PathBuf> {
-    let base = if let Some(path) = cache_base_override {
-        path.to_path_buf()
-    } else {
-        cache_dir().ok_or_else(|| anyhow!("Could not determine cache directory"))?
-    };
-    Ok(base
-        .join("anv")
-        .join("manga-pages")
-        .join(sanitize_cache_segment(manga_id))
+    Ok(manga_cache_dir(manga_id, cache_base_override)?
         .join(translation.as_str())
         .join(sanitize_cache_segment(chapter)))
 }
@@ -1,3 +1,4 @@
 pub mod cache;
 pub mod proxy;
+#[allow(clippy::module_inception)]
 pub mod reader;
@@ -1,14 +1,13 @@
 use crate::{
     adapters::{
-        player::{build_command, detect_player},
+        player_backend::{LaunchRequest, PlayerBackend},
         reader::proxy::{CachedPageTarget, LocalPageProxy},
     },
     types::Page,
 };
 
 use anyhow::{Context, Result, bail};
-use std::path::PathBuf;
-use tokio::process::Command;
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
 
 pub struct DefaultReaderGateway;
 
@@ -18,21 +17,23 @@ pub async fn launch_reader(
     cache_files: &[PathBuf],
     title: &str,
     chapter: &str,
+    player: &Arc<dyn PlayerBackend>,
 ) -> Result<()> {
-    let player = detect_player();
-    let mut cmd = build_command(&player)?;
     let media_title = format!("{title} - Chapter {chapter}");
-    cmd.arg("--quiet");
-    cmd.arg("--terminal=no");
-    cmd.arg(format!("--force-media-title={media_title}"));
-    cmd.arg("--image-display-duration=inf");
+    let no_headers = HashMap::new();
+    let direct_headers = pages.first().map_or(&no_headers, |page| &page.headers);
+    let direct_urls = || pages.iter().map(|page| page.url.clone()).collect();
 
-    if !cached_pages.iter().any(|p| p.is_some()) {
-        add_direct_url_args(&mut cmd, pages);
+    let mut proxy = None;
+    let (urls, headers) = if !cached_pages.iter().any(|p| p.is_some()) {
+        (direct_urls(), direct_headers)
     } else if cached_pages.iter().all(|p| p.is_some()) {
-        for path in cached_pages.iter().flatten() {
-            cmd.arg(path);
-        }
+        let paths = cached_pages
+            .iter()
+            .flatten()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        (paths, &no_headers)
     } else {
         let targets: Vec<CachedPageTarget> = pages
             .iter()
@@ -41,49 +42,40 @@ pub async fn launch_reader(
             .map(|(page, path)| CachedPageTarget { page, path })
             .collect();
         match LocalPageProxy::start(targets) {
-            Ok(mut proxy) => {
-                for idx in 0..pages.len() {
-                    cmd.arg(proxy.page_url(idx));
-                }
-                println!("Launching viewer for Chapter {chapter}...");
-                let status = cmd.status().await.context("failed to launch viewer")?;
-                proxy.shutdown();
-                if !status.success() && status.code() != Some(2) {
-                    bail!("viewer exited with status {status}");
-                }
-                return Ok(());
+            Ok(started) => {
+                let urls = (0..pages.len()).map(|idx| started.page_url(idx)).collect();
+                proxy = Some(started);
+                (urls, &no_headers)
             }
             Err(err) => {
                 eprintln!("Local cache proxy unavailable ({err}). Falling back to direct URLs.");
-                add_direct_url_args(&mut cmd, pages);
+                (direct_urls(), direct_headers)
             }
         }
-    }
+    };
+
+    let mut cmd = player.command(&LaunchRequest {
+        title: &media_title,
+        urls,
+        subtitles: &[],
+        headers,
+        start: None,
+        images: true,
+        ipc_server: None,
+    })?;
 
     println!("Launching viewer for Chapter {chapter}...");
-    let status = cmd.status().await.context("failed to launch viewer")?;
+    let status = cmd.status().await.context("failed to launch viewer");
+    if let Some(mut proxy) = proxy {
+        proxy.shutdown();
+    }
+    let status = status?;
     if !status.success() && status.code() != Some(2) {
         bail!("viewer exited with status {status}");
     }
     Ok(())
 }
 
-fn add_direct_url_args(cmd: &mut Command, pages: &[Page]) {
-    if let Some(first) = pages.first() {
-        for (key, value) in &first.headers {
-            if key.eq_ignore_ascii_case("referer") {
-                cmd.arg(format!("--referrer={value}"));
-                cmd.arg(format!("--http-header-fields=Referer: {value}"));
-            } else {
-                cmd.arg(format!("--http-header-fields={}: {value}", key));
-            }
-        }
-    }
-    for page in pages {
-        cmd.arg(&page.url);
-    }
-}
-
 impl DefaultReaderGateway {
     pub async fn launch_reader(
         &self,
@@ -92,7 +84,8 @@ impl DefaultReaderGateway {
         cache_files: &[PathBuf],
         title: &str,
         chapter: &str,
+        player: &Arc<dyn PlayerBackend>,
     ) -> Result<()> {
-        launch_reader(pages, cached_pages, cache_files, title, chapter).await
+        launch_reader(pages, cached_pages, cache_files, title, chapter, player).await
     }
 }
@@ -0,0 +1,100 @@
+use crate::{adapters::sync_backend::SyncService, types::Provider};
+
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use dirs_next::data_dir;
+use serde::{Deserialize, Serialize};
+
+/// Persistent cache that maps provider show or manga IDs to one sync
+/// service's IDs, loaded per service with [`RemoteIdCache::load_for`] or, for
+/// manga, [`RemoteIdCache::load_manga_for`].
+/// The confirmation dialog is shown only for IDs not yet in this cache.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RemoteIdCache {
+    entries: HashMap<String, u32>,
+    /// Provider each key of `entries` belongs to; missing for IDs cached
+    /// before it was recorded.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    providers: HashMap<String, Provider>,
+    #[serde(skip)]
+    service: SyncService,
+    #[serde(skip)]
+    manga: bool,
+}
+
+impl RemoteIdCache {
+    fn cache_path(service: SyncService, manga: bool) -> Result<PathBuf> {
+        let base = data_dir().ok_or_else(|| anyhow!("Could not determine data directory"))?;
+        let kind = if manga { "_manga" } else { "" };
+        Ok(base
+            .join("anv")
+            .join(format!("{}{kind}_id_cache.json", service.as_str())))
+    }
+
+    pub fn load_for(service: SyncService) -> Result<Self> {
+        Self::load_kind(service, false)
+    }
+
+    pub fn load_manga_for(service: SyncService) -> Result<Self> {
+        Self::load_kind(service, true)
+    }
+
+    fn load_kind(service: SyncService, manga: bool) -> Result<Self> {
+        let path = Self::cache_path(service, manga)?;
+        if !path.exists() {
+            return Ok(Self {
+                service,
+                manga,
+                ..Self::default()
+            });
+        }
+        let data = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read ID cache {}", path.display()))?;
+        let mut cache: Self = serde_json::from_str(&data)
+            .with_context(|| format!("failed to parse ID cache {}", path.display()))?;
+        cache.service = service;
+        cache.manga = manga;
+        Ok(cache)
+    }
+
+    pub fn get(&self, local_id: &str) -> Option<u32> {
+        self.entries.get(local_id).copied()
+    }
+
+    /// Reverse lookup: the `provider` show ID already mapped to `remote_id`.
+    pub fn find_by_remote(&self, provider: Provider, remote_id: u32) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(key, id)| **id == remote_id && self.provider_of(key) == provider)
+            .map(|(key, _)| key.as_str())
+    }
+
+    /// IDs cached before providers were recorded are AllAnime's, which was
+    /// the only provider anv linked to a list service until then.
+    fn provider_of(&self, local_id: &str) -> Provider {
+        self.providers
+            .get(local_id)
+            .copied()
+            .unwrap_or(Provider::Allanime)
+    }
+
+    pub fn insert_and_save(
+        &mut self,
+        provider: Provider,
+        local_id: &str,
+        remote_id: u32,
+    ) -> Result<()> {
+        self.entries.insert(local_id.to_string(), remote_id);
+        self.providers.insert(local_id.to_string(), provider);
+        let path = Self::cache_path(self.service, self.manga)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create data directory {}", parent.display()))?;
+        }
+        let data = serde_json::to_string_pretty(self).context("failed to serialize ID cache")?;
+        fs::write(&path, data)
+            .with_context(|| format!("failed to write ID cache to {}", path.display()))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+use crate::{
+    adapters::hls::VariantPreference,
+    config::{StreamFormat, StreamsConfig, SubtitlesConfig},
+    types::{StreamOption, SubtitleTrack},
+};
+
+/// Orders `options` best-first according to the configured stream policy.
+///
+/// Streams above `max_quality` are dropped unless nothing else is left.
+/// The remaining streams are ranked by closeness to `preferred_quality`
+/// (exact match, then the best below it, then the smallest above it),
+/// then by the preferred container format, then by `source_priority`.
+pub fn order_streams(options: Vec<StreamOption>, policy: &StreamsConfig) -> Vec<StreamOption> {
+    let (mut allowed, rejected): (Vec<_>, Vec<_>) = options
+        .into_iter()
+        .partition(|o| policy.max_quality.is_none_or(|max| within_max(o, max)));
+    if allowed.is_empty() {
+        allowed = rejected;
+    }
+
+    allowed.sort_by_key(|o| {
+        (
+            quality_key(o, policy.preferred_quality),
+            !format_matches(o, policy.format),
+            source_position(o, &policy.source_priority),
+        )
+    });
+    allowed
+}
+
+/// Variant preference to use when a chosen HLS stream still points at a master playlist.
+pub fn variant_preference(policy: &StreamsConfig) -> VariantPreference {
+    match (policy.preferred_quality, policy.max_quality) {
+        (Some(preferred), Some(max)) => VariantPreference::Height(preferred.min(max)),
+        (Some(height), None) | (None, Some(height)) => VariantPreference::Height(height),
+        (None, None) => VariantPreference::Best,
+    }
+}
+
+/// Every track, with the ones in `policy.languages` moved to the front in
+/// preference order and the first of them marked as the default. An empty
+/// language list keeps source order and leaves the choice to the player.
+pub fn select_subtitles(tracks: &[SubtitleTrack], policy: &SubtitlesConfig) -> Vec<SubtitleTrack> {
+    let mut ordered: Vec<SubtitleTrack> = Vec::with_capacity(tracks.len());
+    for wanted in &policy.languages {
+        for track in tracks.iter().filter(|t| t.matches_language(wanted)) {
+            if !ordered.iter().any(|s| s.url == track.url) {
+                ordered.push(track.clone());
+            }
+        }
+    }
+    let preferred = ordered.len();
+    for track in tracks {
+        if !ordered.iter().any(|s| s.url == track.url) {
+            ordered.push(track.clone());
+        }
+    }
+    for (idx, track) in ordered.iter_mut().enumerate() {
+        track.default = idx == 0 && preferred > 0;
+    }
+    ordered
+}
+
+/// `auto` streams have an unknown resolution, so they are never filtered out by a cap.
+fn within_max(option: &StreamOption, max: u32) -> bool {
+    is_auto(option) || option.quality_rank <= max as i32
+}
+
+fn quality_key(option: &StreamOption, preferred: Option<u32>) -> (u8, i32) {
+    let rank = option.quality_rank;
+    match preferred.map(|p| p as i32) {
+        None => (0, -rank),
+        Some(_) if is_auto(option) => (3, 0),
+        Some(p) if rank == p => (0, 0),
+        Some(p) if rank < p => (1, p - rank),
+        Some(p) => (2, rank - p),
+    }
+}
+
+fn format_matches(option: &StreamOption, format: StreamFormat) -> bool {
+    match format {
+        StreamFormat::Any => true,
+        StreamFormat::Hls => option.is_hls,
+        StreamFormat::Mp4 => !option.is_hls,
+    }
+}
+
+fn source_position(option: &StreamOption, priority: &[String]) -> usize {
+    priority
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(&option.provider))
+        .unwrap_or(priority.len())
+}
+
+fn is_auto(option: &StreamOption) -> bool {
+    option.quality_label.eq_ignore_ascii_case("auto")
+}
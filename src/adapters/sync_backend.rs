@@ -0,0 +1,249 @@
+use crate::{
+    adapters::{
+        anilist_client::build_anilist_client_if_enabled,
+        kitsu_client::build_kitsu_client_if_enabled, mal_client::build_mal_client_if_enabled,
+    },
+    config::AppConfig,
+};
+
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// List-tracking services anv can sync watch progress to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncService {
+    #[default]
+    Mal,
+    Anilist,
+    Kitsu,
+}
+
+impl SyncService {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SyncService::Mal => "mal",
+            SyncService::Anilist => "anilist",
+            SyncService::Kitsu => "kitsu",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SyncService::Mal => "MAL",
+            SyncService::Anilist => "AniList",
+            SyncService::Kitsu => "Kitsu",
+        }
+    }
+}
+
+/// Possible watch statuses mirroring MAL's status field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchStatus {
+    Watching,
+    Completed,
+    OnHold,
+    Dropped,
+    PlanToWatch,
+}
+
+impl WatchStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WatchStatus::Watching => "watching",
+            WatchStatus::Completed => "completed",
+            WatchStatus::OnHold => "on_hold",
+            WatchStatus::Dropped => "dropped",
+            WatchStatus::PlanToWatch => "plan_to_watch",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WatchStatus::Watching => "Watching",
+            WatchStatus::Completed => "Completed",
+            WatchStatus::OnHold => "On Hold",
+            WatchStatus::Dropped => "Dropped",
+            WatchStatus::PlanToWatch => "Plan to Watch",
+        }
+    }
+}
+
+/// Reading statuses mirroring MAL's manga status field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadStatus {
+    Reading,
+    Completed,
+    OnHold,
+    Dropped,
+    PlanToRead,
+}
+
+impl ReadStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReadStatus::Reading => "reading",
+            ReadStatus::Completed => "completed",
+            ReadStatus::OnHold => "on_hold",
+            ReadStatus::Dropped => "dropped",
+            ReadStatus::PlanToRead => "plan_to_read",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ReadStatus::Reading => "Reading",
+            ReadStatus::Completed => "Completed",
+            ReadStatus::OnHold => "On Hold",
+            ReadStatus::Dropped => "Dropped",
+            ReadStatus::PlanToRead => "Plan to Read",
+        }
+    }
+}
+
+/// Data passed when updating a list entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncUpdate {
+    /// Anime title as displayed in anv (from AllAnime).
+    pub title: String,
+    /// Current episode just watched (1-indexed integer).
+    pub episode: u32,
+    /// Total number of episodes if known (used to decide Completed vs Watching).
+    pub total_episodes: Option<u32>,
+    pub status: WatchStatus,
+    /// YYYY-MM-DD: set when first adding the anime to the list (not-on-list → watching).
+    pub start_date: Option<String>,
+    /// YYYY-MM-DD: set when the anime is marked completed.
+    pub finish_date: Option<String>,
+    /// User rating 1–10. None means no score change.
+    pub score: Option<u8>,
+}
+
+/// Data passed when updating a manga list entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MangaSyncUpdate {
+    /// Manga title as displayed in anv.
+    pub title: String,
+    /// Chapters read so far; fractional chapters count towards the one before.
+    pub chapter: u32,
+    pub status: ReadStatus,
+    /// YYYY-MM-DD: set when first adding the manga to the list.
+    pub start_date: Option<String>,
+    /// YYYY-MM-DD: set when the manga is marked completed.
+    pub finish_date: Option<String>,
+    /// User rating 1–10. None means no score change.
+    pub score: Option<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CurrentListStatus {
+    /// In MAL's vocabulary (`watching`, `plan_to_watch`, ...) whatever the service.
+    pub status: String,
+    pub num_episodes_watched: u32,
+}
+
+/// Combined per-anime info returned by [`SyncBackend::anime_info`].
+pub struct AnimeInfo {
+    /// The user's current list entry, or `None` if not on their list.
+    pub list_status: Option<CurrentListStatus>,
+    /// Planned total episode count. `0` means unknown / still airing.
+    pub num_episodes: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CurrentMangaStatus {
+    /// In MAL's vocabulary (`reading`, `plan_to_read`, ...).
+    pub status: String,
+    pub num_chapters_read: u32,
+}
+
+/// Combined per-manga info returned by [`SyncBackend::manga_info`].
+pub struct MangaListInfo {
+    /// The user's current list entry, or `None` if not on their list.
+    pub list_status: Option<CurrentMangaStatus>,
+    /// Total chapter count. `0` means unknown / still publishing.
+    pub num_chapters: u32,
+}
+
+/// Returns `true` when a user confirmation prompt is needed before syncing.
+///
+/// Prompt required when:
+/// - Anime is not on the user's list yet (first time → Watching)
+/// - Status is changing (Watching → Completed, etc.)
+///
+/// Silent update when:
+/// - Already Watching and we're just advancing the episode count
+pub fn should_confirm_sync(current: &Option<CurrentListStatus>, new_status: WatchStatus) -> bool {
+    match current {
+        None => true,
+        Some(cur) => cur.status != new_status.as_str(),
+    }
+}
+
+/// A list service that watch progress is pushed to after each episode.
+#[async_trait]
+pub trait SyncBackend: Send + Sync {
+    fn service(&self) -> SyncService;
+
+    /// Searches the service for `title`, shows the top result and asks the
+    /// user to confirm it. `None` if nothing was found or the user declined.
+    async fn resolve_anime_id(&self, title: &str) -> Result<Option<u32>>;
+
+    /// The user's list entry for `id` and the planned episode count.
+    async fn anime_info(&self, id: u32) -> Result<AnimeInfo>;
+
+    async fn update_anime(&self, id: u32, update: &SyncUpdate) -> Result<()>;
+
+    /// Whether the service tracks manga. The manga methods below are only
+    /// called when it does.
+    fn supports_manga(&self) -> bool {
+        false
+    }
+
+    /// Like [`SyncBackend::resolve_anime_id`], for a manga title.
+    async fn resolve_manga_id(&self, _title: &str) -> Result<Option<u32>> {
+        bail!("{} manga sync is not supported", self.service().label())
+    }
+
+    /// The user's list entry for manga `id` and its chapter count.
+    async fn manga_info(&self, _id: u32) -> Result<MangaListInfo> {
+        bail!("{} manga sync is not supported", self.service().label())
+    }
+
+    async fn update_manga(&self, _id: u32, _update: &MangaSyncUpdate) -> Result<()> {
+        bail!("{} manga sync is not supported", self.service().label())
+    }
+}
+
+/// Builds a client for each service in `sync.services` that has a stored
+/// token. Empty when sync is disabled.
+pub async fn build_sync_backends(cfg: &AppConfig) -> Vec<Box<dyn SyncBackend>> {
+    let mut backends: Vec<Box<dyn SyncBackend>> = Vec::new();
+    if !cfg.sync.enabled {
+        return backends;
+    }
+    let mut seen = Vec::new();
+    for &service in &cfg.sync.services {
+        if seen.contains(&service) {
+            continue;
+        }
+        seen.push(service);
+        let backend: Option<Box<dyn SyncBackend>> = match service {
+            SyncService::Mal => build_mal_client_if_enabled(cfg)
+                .await
+                .map(|client| Box::new(client) as _),
+            SyncService::Anilist => {
+                build_anilist_client_if_enabled().map(|client| Box::new(client) as _)
+            }
+            SyncService::Kitsu => build_kitsu_client_if_enabled()
+                .await
+                .map(|client| Box::new(client) as _),
+        };
+        backends.extend(backend);
+    }
+    backends
+}
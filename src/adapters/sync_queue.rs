@@ -0,0 +1,231 @@
+use crate::adapters::sync_backend::{MangaSyncUpdate, SyncBackend, SyncService, SyncUpdate};
+
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Utc};
+use dirs_next::data_dir;
+use serde::{Deserialize, Serialize};
+
+/// A list update that could not be sent, kept until it can be replayed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedUpdate {
+    pub service: SyncService,
+    /// The entry's ID on `service`.
+    pub id: u32,
+    pub change: QueuedChange,
+    pub queued_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum QueuedChange {
+    Anime(SyncUpdate),
+    Manga(MangaSyncUpdate),
+}
+
+impl QueuedChange {
+    fn is_manga(&self) -> bool {
+        matches!(self, QueuedChange::Manga(_))
+    }
+
+    /// One-line description for `anv sync status`.
+    pub fn summary(&self) -> String {
+        match self {
+            QueuedChange::Anime(update) => format!(
+                "\"{}\" ep {} → {}",
+                update.title,
+                update.episode,
+                update.status.label()
+            ),
+            QueuedChange::Manga(update) => format!(
+                "\"{}\" ch {} → {}",
+                update.title,
+                update.chapter,
+                update.status.label()
+            ),
+        }
+    }
+
+    /// Folds an older pending change for the same entry into this one. The
+    /// newer state wins, but dates and a score only the older one carried are
+    /// kept so they still reach the service.
+    fn absorb(&mut self, older: QueuedChange) {
+        match (self, older) {
+            (QueuedChange::Anime(new), QueuedChange::Anime(old)) => {
+                new.start_date = new.start_date.take().or(old.start_date);
+                new.finish_date = new.finish_date.take().or(old.finish_date);
+                new.score = new.score.or(old.score);
+            }
+            (QueuedChange::Manga(new), QueuedChange::Manga(old)) => {
+                new.start_date = new.start_date.take().or(old.start_date);
+                new.finish_date = new.finish_date.take().or(old.finish_date);
+                new.score = new.score.or(old.score);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Failed list updates, persisted across runs and merged so that only the
+/// latest state per entry is ever sent.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SyncQueue {
+    pub entries: Vec<QueuedUpdate>,
+}
+
+/// Outcome of [`SyncQueue::flush`].
+#[derive(Debug, Default)]
+pub struct FlushReport {
+    pub sent: usize,
+    pub failed: usize,
+    /// Entries for services that are not currently enabled.
+    pub skipped: usize,
+}
+
+impl SyncQueue {
+    fn queue_path() -> Result<PathBuf> {
+        let base = data_dir().ok_or_else(|| anyhow!("Could not determine data directory"))?;
+        Ok(base.join("anv").join("sync_queue.json"))
+    }
+
+    pub fn load() -> Result<Self> {
+        let path = Self::queue_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let data = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read sync queue {}", path.display()))?;
+        serde_json::from_str(&data)
+            .with_context(|| format!("failed to parse sync queue {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::queue_path()?;
+        if self.entries.is_empty() {
+            if path.exists() {
+                fs::remove_file(&path)
+                    .with_context(|| format!("failed to remove {}", path.display()))?;
+            }
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create data directory {}", parent.display()))?;
+        }
+        let data = serde_json::to_string_pretty(self).context("failed to serialize sync queue")?;
+        fs::write(&path, data)
+            .with_context(|| format!("failed to write sync queue to {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Adds `change`, replacing any pending change for the same entry.
+    pub fn push(&mut self, service: SyncService, id: u32, mut change: QueuedChange) {
+        if let Some(pos) = self.entries.iter().position(|entry| {
+            entry.service == service
+                && entry.id == id
+                && entry.change.is_manga() == change.is_manga()
+        }) {
+            change.absorb(self.entries.remove(pos).change);
+        }
+        self.entries.push(QueuedUpdate {
+            service,
+            id,
+            change,
+            queued_at: Utc::now(),
+        });
+    }
+
+    /// Loads the queue, adds `change` and saves it again.
+    pub fn enqueue(service: SyncService, id: u32, change: QueuedChange) -> Result<()> {
+        let mut queue = Self::load()?;
+        queue.push(service, id, change);
+        queue.save()
+    }
+
+    /// Sends every pending change whose service is in `backends`. Sent
+    /// changes are dropped from the queue; failed ones stay for next time.
+    pub async fn flush(&mut self, backends: &[Box<dyn SyncBackend>]) -> FlushReport {
+        let mut report = FlushReport::default();
+        let mut pending = Vec::new();
+        for entry in std::mem::take(&mut self.entries) {
+            let Some(backend) = backends.iter().find(|b| b.service() == entry.service) else {
+                report.skipped += 1;
+                pending.push(entry);
+                continue;
+            };
+            let sent = match &entry.change {
+                QueuedChange::Anime(update) => backend.update_anime(entry.id, update).await,
+                QueuedChange::Manga(update) => backend.update_manga(entry.id, update).await,
+            };
+            match sent {
+                Ok(()) => report.sent += 1,
+                Err(err) => {
+                    eprintln!(
+                        "[sync] Could not send queued {} update {}: {err}",
+                        entry.service.label(),
+                        entry.change.summary()
+                    );
+                    report.failed += 1;
+                    pending.push(entry);
+                }
+            }
+        }
+        self.entries = pending;
+        report
+    }
+}
+
+/// Queues `change` after a failed update, reporting (not returning) errors so
+/// callers can carry on playing.
+pub fn queue_failed_update(service: SyncService, id: u32, change: QueuedChange) {
+    match SyncQueue::enqueue(service, id, change) {
+        Ok(()) => println!(
+            "[sync] Queued the {} update; it will be retried.",
+            service.label()
+        ),
+        Err(err) => eprintln!("[sync] Could not queue the update for retry: {err}"),
+    }
+}
+
+/// Drops a pending change that a successful live update has superseded, so
+/// a later replay cannot move the entry back to an older state.
+pub fn discard_queued_update(service: SyncService, id: u32, manga: bool) {
+    let discarded = SyncQueue::load().and_then(|mut queue| {
+        let before = queue.entries.len();
+        queue.entries.retain(|entry| {
+            !(entry.service == service && entry.id == id && entry.change.is_manga() == manga)
+        });
+        if queue.entries.len() == before {
+            return Ok(());
+        }
+        queue.save()
+    });
+    if let Err(err) = discarded {
+        eprintln!("[sync] Could not update the sync queue: {err}");
+    }
+}
+
+/// Replays queued updates on launch. Quiet unless something was pending.
+pub async fn replay_sync_queue(backends: &[Box<dyn SyncBackend>]) {
+    if backends.is_empty() {
+        return;
+    }
+    let mut queue = match SyncQueue::load() {
+        Ok(queue) => queue,
+        Err(err) => {
+            eprintln!("[sync] Could not load the sync queue: {err}");
+            return;
+        }
+    };
+    if queue.entries.is_empty() {
+        return;
+    }
+    let report = queue.flush(backends).await;
+    if let Err(err) = queue.save() {
+        eprintln!("[sync] Could not save the sync queue: {err}");
+    }
+    if report.sent > 0 {
+        println!("[sync] Sent {} queued update(s).", report.sent);
+    }
+}
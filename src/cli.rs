@@ -1,11 +1,11 @@
-use crate::types::Provider;
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use crate::{adapters::sync_backend::SyncService, types::Provider};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::{path::PathBuf, str::FromStr};
 
 #[derive(Debug, Parser)]
 #[command(name = "anv", about = "Stream anime or read manga via mpv.", version)]
 pub struct Cli {
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub dub: bool,
 
     #[arg(long)]
@@ -17,12 +17,27 @@ pub struct Cli {
     #[arg(long)]
     pub binge: bool,
 
-    #[arg(long, default_value = "allanime", value_enum)]
-    pub provider: Provider,
+    /// Always show the stream picker instead of applying the `[streams]` policy.
+    #[arg(long)]
+    pub pick_stream: bool,
+
+    /// Provider to search (see `anv providers`), or `all` to search every
+    /// manga provider at once.
+    #[arg(long, default_value = "allanime", value_name = "PROVIDER")]
+    pub provider: ProviderSelection,
 
     #[arg(long, value_name = "DIR")]
     pub cache_dir: Option<PathBuf>,
 
+    /// Ignore cached search results and episode/chapter lists and fetch them again.
+    #[arg(long, global = true)]
+    pub refresh: bool,
+
+    /// Browse and play only what is on disk: downloaded episodes and cached
+    /// manga chapters.
+    #[arg(long, global = true)]
+    pub offline: bool,
+
     #[arg(short = 'e', long, value_name = "EPISODE")]
     pub episode: Option<String>,
 
@@ -33,10 +48,95 @@ pub struct Cli {
     pub command: Option<Commands>,
 }
 
+/// Value of `--provider`: one provider, or every provider (`all`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderSelection {
+    One(Provider),
+    All,
+}
+
+impl ProviderSelection {
+    /// The provider to use where searching everything does not apply (anime,
+    /// downloads); `all` falls back to the default provider there.
+    pub fn provider(self) -> Provider {
+        match self {
+            Self::One(provider) => provider,
+            Self::All => Provider::default(),
+        }
+    }
+}
+
+impl FromStr for ProviderSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("all") {
+            return Ok(Self::All);
+        }
+        Provider::from_str(s, true).map(Self::One).map_err(|_| {
+            let names: Vec<String> = Provider::value_variants()
+                .iter()
+                .filter_map(|p| p.to_possible_value())
+                .map(|v| v.get_name().to_string())
+                .collect();
+            format!(
+                "unknown provider '{s}' (expected {} or all)",
+                names.join(", ")
+            )
+        })
+    }
+}
+
 #[derive(Debug, Subcommand)]
 pub enum Commands {
     /// Open watch/read history and replay an entry.
     History,
+    /// Download episodes to disk for offline viewing.
+    Download {
+        /// Episodes to download, e.g. `5`, `1-12` or `1-3,7`.
+        #[arg(short = 'e', long, value_name = "RANGE")]
+        episodes: Option<String>,
+
+        /// Preferred stream quality, e.g. `1080` or `720p` (default: `[streams]` config).
+        #[arg(short = 'q', long, value_name = "QUALITY")]
+        quality: Option<String>,
+
+        /// Directory to save episodes into (default: <downloads>/anv).
+        #[arg(short = 'o', long, value_name = "DIR")]
+        output: Option<PathBuf>,
+
+        #[arg(value_name = "QUERY")]
+        query: Vec<String>,
+    },
+    /// Pack manga chapters into CBZ archives (or PDFs) for e-readers and tablets.
+    Export {
+        /// Chapters to export, e.g. `12`, `1-10` or `1-3,7`.
+        #[arg(short = 'c', long, value_name = "RANGE")]
+        chapters: Option<String>,
+
+        /// Formats to write, comma-separated.
+        #[arg(
+            short = 'f',
+            long,
+            value_enum,
+            value_delimiter = ',',
+            default_value = "cbz"
+        )]
+        format: Vec<ExportFormat>,
+
+        /// Directory to save files into (default: <downloads>/anv).
+        #[arg(short = 'o', long, value_name = "DIR")]
+        output: Option<PathBuf>,
+
+        #[arg(value_name = "QUERY")]
+        query: Vec<String>,
+    },
+    /// List providers and what they support (anime, manga, sub, dub, raw).
+    Providers {
+        /// Show a single provider.
+        #[arg(value_name = "NAME")]
+        name: Option<String>,
+    },
     /// Manage sync with external anime list services.
     Sync {
         #[command(subcommand)]
@@ -44,12 +144,36 @@ pub enum Commands {
     },
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// Comic book archive with `ComicInfo.xml` metadata.
+    Cbz,
+    Pdf,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Cbz => "cbz",
+            ExportFormat::Pdf => "pdf",
+        }
+    }
+}
+
 #[derive(Debug, Subcommand)]
 pub enum SyncAction {
-    /// Enable MAL sync and authenticate.
-    Enable,
-    /// Show current sync status and MAL authentication state.
+    /// Authenticate with a list service so sync can be enabled.
+    Enable {
+        #[arg(long, value_enum, default_value = "mal")]
+        service: SyncService,
+    },
+    /// Show current sync status and authentication state.
     Status,
+    /// Send list updates that failed earlier and were queued for retry.
+    Flush,
+    /// Import your MAL watching and plan-to-watch lists into history. Only
+    /// exact title matches on AllAnime are imported.
+    Pull,
     /// Disable MAL sync (can be re-enabled by editing config).
     Disable,
 }
@@ -0,0 +1,175 @@
+use crate::{
+    adapters::{
+        downloader::{
+            default_download_dir, download_stream, download_subtitles, episode_file_path,
+            existing_download,
+        },
+        providers::registry,
+        stream_policy::{order_streams, select_subtitles, variant_preference},
+    },
+    cli::Cli,
+    config::AppConfig,
+    library::{LibraryInfo, record_download_root},
+    progression::{labels_in_range, sorted_labels_numeric},
+    prompt::{select_episode, select_show_entry},
+    types::Translation,
+};
+
+use anyhow::{Result, bail};
+use std::path::Path;
+
+pub async fn run_download_command(
+    cli: &Cli,
+    config: &AppConfig,
+    query: &[String],
+    episodes: Option<&str>,
+    quality: Option<&str>,
+    output: Option<&Path>,
+) -> Result<()> {
+    let translation = if cli.dub {
+        Translation::Dub
+    } else {
+        Translation::Sub
+    };
+
+    let provider = registry::anime_provider(cli.provider.provider());
+
+    if query.is_empty() {
+        println!("No query provided. Use `anv download <name> -e <range>`.");
+        return Ok(());
+    }
+
+    let client = provider.anime_client(&config.streams)?;
+    let query = query.join(" ");
+    let shows = client.search_shows(&query, translation).await?;
+    if shows.is_empty() {
+        bail!("No results for \"{}\" ({})", query, translation.label());
+    }
+
+    let Some(show) = select_show_entry(&shows, translation)? else {
+        println!("Cancelled.");
+        return Ok(());
+    };
+
+    let available = client.fetch_episodes(&show.id, translation).await?;
+    if available.is_empty() {
+        bail!(
+            "No {} episodes available for {}",
+            translation.label(),
+            show.title
+        );
+    }
+    let sorted_episodes = sorted_labels_numeric(&available);
+
+    let targets = match episodes {
+        Some(spec) => labels_in_range(spec, &sorted_episodes)?,
+        None => {
+            let default_idx = sorted_episodes.len() - 1;
+            let Some(idx) = select_episode(
+                &sorted_episodes,
+                default_idx,
+                "Episode to download (type to search, Esc to cancel)",
+            )?
+            else {
+                println!("Cancelled.");
+                return Ok(());
+            };
+            vec![sorted_episodes[idx].clone()]
+        }
+    };
+
+    let base = match output {
+        Some(dir) => dir.to_path_buf(),
+        None => default_download_dir()?,
+    };
+
+    let mut policy = config.streams.clone();
+    if let Some(height) = quality.and_then(parse_quality) {
+        policy.preferred_quality = Some(height);
+        policy.max_quality = None;
+    }
+    let preference = variant_preference(&policy);
+
+    let mut failed: Vec<String> = Vec::new();
+    for (pos, episode) in targets.iter().enumerate() {
+        let dest = episode_file_path(&base, &show.title, translation, episode);
+        if let Some(existing) = existing_download(&dest) {
+            println!(
+                "[{}/{}] Episode {} already downloaded: {}",
+                pos + 1,
+                targets.len(),
+                episode,
+                existing.display()
+            );
+            continue;
+        }
+
+        println!(
+            "[{}/{}] Fetching streams for episode {}...",
+            pos + 1,
+            targets.len(),
+            episode
+        );
+        let streams = match client.fetch_streams(&show.id, translation, episode).await {
+            Ok(streams) => streams,
+            Err(err) => {
+                eprintln!("Error fetching streams for episode {episode}: {err}");
+                failed.push(episode.clone());
+                continue;
+            }
+        };
+
+        let Some(mut stream) = order_streams(streams, &policy).into_iter().next() else {
+            eprintln!("No supported streams found for episode {episode}.");
+            failed.push(episode.clone());
+            continue;
+        };
+
+        println!("Downloading {}...", stream.label());
+        match download_stream(&stream, &dest, preference).await {
+            Ok(path) => {
+                println!("Saved {}", path.display());
+                if let Some(dir) = dest.parent() {
+                    let info = LibraryInfo {
+                        id: show.id.clone(),
+                        title: show.title.clone(),
+                        provider: provider.provider,
+                        translation: Some(translation),
+                    };
+                    if let Err(err) = info.write(dir).and_then(|()| record_download_root(&base)) {
+                        eprintln!("Warning: could not record download in the library: {err:#}");
+                    }
+                }
+                stream.subtitles = select_subtitles(&stream.subtitles, &config.subtitles);
+                if config.subtitles.save_with_downloads && !stream.subtitles.is_empty() {
+                    match download_subtitles(&stream, &dest).await {
+                        Ok(saved) => println!("Saved {} subtitle file(s).", saved.len()),
+                        Err(err) => {
+                            eprintln!("Could not save subtitles for episode {episode}: {err:#}")
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                eprintln!("Failed to download episode {episode}: {err:#}");
+                failed.push(episode.clone());
+            }
+        }
+    }
+
+    if failed.is_empty() {
+        println!(
+            "Downloaded {} episode(s) to {}.",
+            targets.len(),
+            base.display()
+        );
+        Ok(())
+    } else {
+        bail!("Failed to download episode(s): {}", failed.join(", "))
+    }
+}
+
+/// Parses a quality flag such as `"720"` or `"720p"` into a frame height.
+fn parse_quality(quality: &str) -> Option<u32> {
+    quality.trim().trim_end_matches('p').parse().ok()
+}
@@ -0,0 +1,182 @@
+use crate::{
+    adapters::{
+        downloader::default_download_dir,
+        export::{ComicInfo, chapter_file_path, write_cbz, write_pdf},
+        providers::registry,
+        reader::cache::cache_manga_pages,
+    },
+    cli::{Cli, ExportFormat},
+    commands::read_manga::{record_in_library, search_and_select},
+    config::MangaConfig,
+    progression::{labels_in_range, sorted_labels_numeric},
+    prompt::select_episode,
+    types::Translation,
+};
+
+use anyhow::{Context, Result, bail};
+use std::{fs, path::Path};
+
+pub async fn run_export_command(
+    cli: &Cli,
+    manga_config: &MangaConfig,
+    query: &[String],
+    chapters: Option<&str>,
+    formats: &[ExportFormat],
+    output: Option<&Path>,
+) -> Result<()> {
+    let translation = if cli.raw {
+        Translation::Raw
+    } else {
+        Translation::Sub
+    };
+
+    if query.is_empty() {
+        println!("No query provided. Use `anv export <name> -c <range>`.");
+        return Ok(());
+    }
+
+    let query = query.join(" ");
+    let Some((provider, manga)) =
+        search_and_select(cli.provider, &query, translation, manga_config).await?
+    else {
+        println!("Cancelled.");
+        return Ok(());
+    };
+
+    let client = registry::descriptor(provider).manga_client()?;
+    let available = client.fetch_chapters(&manga.id, translation).await?;
+    if available.is_empty() {
+        bail!(
+            "No {} chapters available for {}",
+            translation.label(),
+            manga.title
+        );
+    }
+    let labels: Vec<String> = available.iter().map(|c| c.label.clone()).collect();
+    let sorted_labels = sorted_labels_numeric(&labels);
+
+    let targets = match chapters {
+        Some(spec) => labels_in_range(spec, &sorted_labels)?,
+        None => {
+            let default_idx = sorted_labels.len() - 1;
+            let Some(idx) = select_episode(
+                &sorted_labels,
+                default_idx,
+                "Chapter to export (type to search, Esc to cancel)",
+            )?
+            else {
+                println!("Cancelled.");
+                return Ok(());
+            };
+            vec![sorted_labels[idx].clone()]
+        }
+    };
+
+    let base = match output {
+        Some(dir) => dir.to_path_buf(),
+        None => default_download_dir()?,
+    };
+    let language = (translation == Translation::Sub).then_some("en");
+
+    let mut failed: Vec<String> = Vec::new();
+    for (pos, label) in targets.iter().enumerate() {
+        let progress = format!("[{}/{}]", pos + 1, targets.len());
+        let dests: Vec<_> = formats
+            .iter()
+            .map(|format| {
+                let path = chapter_file_path(&base, &manga.title, label, format.extension());
+                (*format, path)
+            })
+            .filter(|(_, path)| !path.exists())
+            .collect();
+        if dests.is_empty() {
+            println!("{progress} Chapter {label} already exported.");
+            continue;
+        }
+
+        let Some(chapter) = available.iter().find(|c| &c.label == label) else {
+            failed.push(label.clone());
+            continue;
+        };
+        println!("{progress} Fetching pages for chapter {label}...");
+        let pages = match client
+            .fetch_pages(&manga.id, translation, &chapter.id)
+            .await
+        {
+            Ok(pages) if !pages.is_empty() => pages,
+            Ok(_) => {
+                eprintln!("No pages found for chapter {label}.");
+                failed.push(label.clone());
+                continue;
+            }
+            Err(err) => {
+                eprintln!("Error fetching pages for chapter {label}: {err}");
+                failed.push(label.clone());
+                continue;
+            }
+        };
+
+        let state = cache_manga_pages(
+            &pages,
+            &manga.id,
+            translation,
+            label,
+            cli.cache_dir.as_deref(),
+            pages.len(),
+        )
+        .await?;
+        record_in_library(&manga, provider, cli.cache_dir.as_deref());
+        let files: Vec<_> = state.cached_pages.iter().flatten().cloned().collect();
+        if state.cdn_blocked || files.len() < pages.len() {
+            eprintln!(
+                "Only {}/{} pages of chapter {label} could be downloaded.",
+                files.len(),
+                pages.len()
+            );
+            failed.push(label.clone());
+            continue;
+        }
+
+        let title = format!("Chapter {label}");
+        for (format, dest) in dests {
+            if let Some(dir) = dest.parent() {
+                fs::create_dir_all(dir)
+                    .with_context(|| format!("failed to create directory {}", dir.display()))?;
+            }
+            let written = match format {
+                ExportFormat::Cbz => {
+                    let info = ComicInfo {
+                        series: &manga.title,
+                        number: label,
+                        title: &title,
+                        page_count: files.len(),
+                        language,
+                    };
+                    write_cbz(&files, &info, &dest)
+                }
+                ExportFormat::Pdf => {
+                    write_pdf(&files, &format!("{} - {title}", manga.title), &dest).await
+                }
+            };
+            match written {
+                Ok(()) => println!("Saved {}", dest.display()),
+                Err(err) => {
+                    eprintln!("Failed to export chapter {label}: {err:#}");
+                    failed.push(label.clone());
+                    break;
+                }
+            }
+        }
+    }
+
+    if failed.is_empty() {
+        println!(
+            "Exported {} chapter(s) to {}.",
+            targets.len(),
+            base.display()
+        );
+        Ok(())
+    } else {
+        bail!("Failed to export chapter(s): {}", failed.join(", "))
+    }
+}
@@ -1,15 +1,14 @@
 use crate::{
-    adapters::{
-        mal_client::MalClient,
-        providers::{
-            allanime::AllAnimeClient, mangadex::MangaDexClient, mangapill::MangapillClient,
-        },
-    },
+    adapters::{providers::registry, sync_backend::SyncBackend},
     cli::Cli,
-    commands::{play_anime, read_manga},
+    commands::{
+        play_anime::{self, PlaybackSettings},
+        read_manga,
+    },
+    config::MangaConfig,
     history::History,
     prompt::select_history_entry,
-    types::{ChapterCounts, EpisodeCounts, MangaInfo, Provider, ShowInfo},
+    types::{ChapterCounts, EpisodeCounts, MangaInfo, ShowInfo},
 };
 
 use anyhow::Result;
@@ -18,9 +17,9 @@ pub async fn run_history_command(
     cli: &Cli,
     history: &mut History,
     history_mode: bool,
-    player: &String,
-    mal_client: Option<&MalClient>,
-    binge: bool,
+    settings: &PlaybackSettings,
+    manga_config: &MangaConfig,
+    sync: &[Box<dyn SyncBackend>],
 ) -> Result<()> {
     if history_mode {
         if let Some(entry) = select_history_entry(history)? {
@@ -30,44 +29,18 @@ pub async fn run_history_command(
                     title: entry.show_title.clone(),
                     available_chapters: ChapterCounts::default(),
                 };
-                match entry.provider {
-                    Provider::Allanime => {
-                        read_manga::read_manga(
-                            history,
-                            &AllAnimeClient::new()?,
-                            entry.translation,
-                            &manga_info,
-                            Some(entry.episode.clone()),
-                            cli.cache_dir.as_deref(),
-                            entry.provider,
-                        )
-                        .await?
-                    }
-                    Provider::Mangadex => {
-                        read_manga::read_manga(
-                            history,
-                            &MangaDexClient::new()?,
-                            entry.translation,
-                            &manga_info,
-                            Some(entry.episode.clone()),
-                            cli.cache_dir.as_deref(),
-                            entry.provider,
-                        )
-                        .await?
-                    }
-                    Provider::Mangapill => {
-                        read_manga::read_manga(
-                            history,
-                            &MangapillClient::new()?,
-                            entry.translation,
-                            &manga_info,
-                            Some(entry.episode.clone()),
-                            cli.cache_dir.as_deref(),
-                            entry.provider,
-                        )
-                        .await?
-                    }
-                }
+                read_manga::read_with_fallback(
+                    history,
+                    entry.translation,
+                    manga_info,
+                    entry.provider,
+                    Some(entry.episode.clone()),
+                    cli.cache_dir.as_deref(),
+                    manga_config,
+                    &settings.player,
+                    sync,
+                )
+                .await?
             } else {
                 let show_info = ShowInfo {
                     id: entry.show_id.clone(),
@@ -75,16 +48,17 @@ pub async fn run_history_command(
                     available_eps: EpisodeCounts::default(),
                 };
 
+                let client =
+                    registry::anime_provider(entry.provider).anime_client(&settings.streams)?;
                 play_anime::play_show(
                     history,
-                    &AllAnimeClient::new()?,
+                    client.as_ref(),
                     entry.translation,
                     entry.provider,
                     &show_info,
-                    Some(entry.episode.clone()),
-                    player,
-                    mal_client,
-                    binge,
+                    Some(entry.continue_episode().unwrap_or(entry.episode.clone())),
+                    settings,
+                    sync,
                 )
                 .await?;
             }
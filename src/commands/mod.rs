@@ -1,4 +1,8 @@
+pub mod download_anime;
+pub mod export_manga;
 pub mod history;
+pub mod offline;
 pub mod play_anime;
+pub mod providers;
 pub mod read_manga;
-pub mod sync_mal;
+pub mod sync;
@@ -0,0 +1,290 @@
+use crate::{
+    adapters::{
+        player::{PlayerExit, launch_player},
+        reader::reader::launch_reader,
+    },
+    cli::Cli,
+    commands::play_anime::{PlaybackSettings, record_playback},
+    history::{History, HistoryEntry},
+    library::{Library, LocalManga, LocalShow},
+    prompt::{select_episode, select_history_entry},
+    types::{EpisodeCounts, Page, ShowInfo, StreamOption, normalize_title},
+};
+
+use anyhow::{Result, bail};
+use chrono::Utc;
+use std::collections::HashMap;
+
+/// Browses what is on disk (downloaded episodes and cached manga pages) and
+/// plays or reads it without touching the network. History is still updated.
+pub async fn run_offline_command(
+    cli: &Cli,
+    history: &mut History,
+    history_mode: bool,
+    settings: &PlaybackSettings,
+) -> Result<()> {
+    let library = Library::scan(history, cli.cache_dir.as_deref())?;
+
+    if history_mode {
+        let Some(entry) = select_history_entry(history)? else {
+            return Ok(());
+        };
+        if entry.is_manga {
+            let Some(manga) = library.manga(&entry.show_id, entry.translation) else {
+                bail!("\"{}\" has no cached chapters.", entry.show_title);
+            };
+            return read_local_manga(history, settings, manga, Some(entry.episode)).await;
+        }
+        let Some(show) = library.show(&entry.show_id, entry.translation) else {
+            bail!("\"{}\" has no downloaded episodes.", entry.show_title);
+        };
+        return watch_local_show(history, settings, show, Some(entry.episode)).await;
+    }
+
+    let query = normalize_title(&cli.query.join(" "));
+    let matches = |title: &str| query.is_empty() || normalize_title(title).contains(&query);
+
+    if cli.manga {
+        let mangas: Vec<&LocalManga> = library
+            .mangas
+            .iter()
+            .filter(|m| matches(&m.title))
+            .collect();
+        if mangas.is_empty() {
+            println!("No cached manga found. Chapters are kept once read online.");
+            return Ok(());
+        }
+        let items: Vec<String> = mangas
+            .iter()
+            .map(|m| {
+                format!(
+                    "{} [{}] · {} chapter(s)",
+                    m.title,
+                    m.translation.label(),
+                    m.chapters.len()
+                )
+            })
+            .collect();
+        let Some(idx) = select_episode(&items, 0, "Select a manga (offline, Esc to cancel)")?
+        else {
+            println!("Cancelled.");
+            return Ok(());
+        };
+        return read_local_manga(history, settings, mangas[idx], cli.episode.clone()).await;
+    }
+
+    let shows: Vec<&LocalShow> = library.shows.iter().filter(|s| matches(&s.title)).collect();
+    if shows.is_empty() {
+        println!("No downloaded episodes found. Use `anv download` to save some.");
+        return Ok(());
+    }
+    let items: Vec<String> = shows
+        .iter()
+        .map(|s| {
+            format!(
+                "{} [{}] · {} episode(s)",
+                s.title,
+                s.translation.label(),
+                s.episodes.len()
+            )
+        })
+        .collect();
+    let Some(idx) = select_episode(&items, 0, "Select a show (offline, Esc to cancel)")? else {
+        println!("Cancelled.");
+        return Ok(());
+    };
+    watch_local_show(history, settings, shows[idx], cli.episode.clone()).await
+}
+
+/// Where to start: the requested label if it is on disk, else the last one
+/// seen, else the first one available.
+fn starting_point(
+    labels: &[String],
+    prefer: Option<String>,
+    last: Option<String>,
+) -> (usize, bool) {
+    let position = |label: &String| labels.iter().position(|l| l == label);
+    if let Some(idx) = prefer.as_ref().and_then(position) {
+        return (idx, true);
+    }
+    if let Some(label) = &prefer {
+        println!("'{label}' is not available offline. Showing what is.");
+    }
+    (last.as_ref().and_then(position).unwrap_or(0), false)
+}
+
+async fn watch_local_show(
+    history: &mut History,
+    settings: &PlaybackSettings,
+    show: &LocalShow,
+    prefer_episode: Option<String>,
+) -> Result<()> {
+    let labels = show.labels();
+    let info = ShowInfo {
+        id: show.id.clone(),
+        title: show.title.clone(),
+        available_eps: EpisodeCounts::default(),
+    };
+    let last_watched = history.last_episode(&show.id, show.translation);
+    let (mut default_idx, mut skip_selection) =
+        starting_point(&labels, prefer_episode, last_watched);
+
+    loop {
+        let idx = if skip_selection {
+            skip_selection = false;
+            default_idx
+        } else {
+            let Some(i) = select_episode(
+                &labels,
+                default_idx,
+                "Episode to play (offline, Esc to cancel)",
+            )?
+            else {
+                println!("Exiting playback loop.");
+                return Ok(());
+            };
+            i
+        };
+
+        let episode = &show.episodes[idx];
+        let stream = StreamOption {
+            provider: String::from("local"),
+            url: episode.path.to_string_lossy().into_owned(),
+            quality_label: String::from("file"),
+            quality_rank: 0,
+            is_hls: false,
+            headers: HashMap::new(),
+            subtitles: episode.subtitles.clone(),
+        };
+        let resume_at = history.resume_position(&show.id, show.translation, &episode.label);
+        println!("Playing {}", episode.path.display());
+        let outcome = launch_player(
+            &stream,
+            &show.title,
+            &episode.label,
+            &settings.player,
+            resume_at,
+        )
+        .await?;
+        if outcome.exit == PlayerExit::StreamFailed {
+            bail!("Could not play {}.", episode.path.display());
+        }
+
+        let watched = record_playback(
+            history,
+            settings,
+            &info,
+            show.translation,
+            show.provider,
+            &episode.label,
+            outcome.progress,
+        )?;
+        if !watched {
+            if settings.binge {
+                println!("Exiting playback loop.");
+                return Ok(());
+            }
+            default_idx = idx;
+            continue;
+        }
+
+        if idx + 1 < labels.len() {
+            default_idx = idx + 1;
+            skip_selection = settings.binge;
+        } else {
+            println!("No further downloaded episodes. Exiting.");
+            return Ok(());
+        }
+    }
+}
+
+async fn read_local_manga(
+    history: &mut History,
+    settings: &PlaybackSettings,
+    manga: &LocalManga,
+    prefer_chapter: Option<String>,
+) -> Result<()> {
+    let labels = manga.labels();
+    let items: Vec<String> = manga
+        .chapters
+        .iter()
+        .map(|chapter| {
+            if chapter.is_complete() {
+                chapter.label.clone()
+            } else {
+                format!("{} (partial)", chapter.label)
+            }
+        })
+        .collect();
+    let last_read = history.last_chapter(&manga.id, manga.translation);
+    let (mut default_idx, mut skip_selection) = starting_point(&labels, prefer_chapter, last_read);
+
+    loop {
+        let idx = if skip_selection {
+            skip_selection = false;
+            default_idx
+        } else {
+            let Some(i) = select_episode(
+                &items,
+                default_idx,
+                "Chapter to read (offline, Esc to cancel)",
+            )?
+            else {
+                println!("Exiting reading loop.");
+                return Ok(());
+            };
+            i
+        };
+
+        let chapter = &manga.chapters[idx];
+        let pages: Vec<Page> = chapter
+            .pages
+            .iter()
+            .map(|path| Page {
+                url: path.to_string_lossy().into_owned(),
+                headers: HashMap::new(),
+            })
+            .collect();
+        let cached_pages: Vec<_> = chapter.pages.iter().cloned().map(Some).collect();
+        launch_reader(
+            &pages,
+            &cached_pages,
+            &chapter.pages,
+            &manga.title,
+            &chapter.label,
+            &settings.player,
+        )
+        .await?;
+
+        if chapter.is_complete() {
+            history.upsert(HistoryEntry {
+                show_id: manga.id.clone(),
+                show_title: manga.title.clone(),
+                episode: chapter.label.clone(),
+                translation: manga.translation,
+                provider: manga.provider,
+                is_manga: true,
+                watched_at: Utc::now(),
+                position: None,
+                duration: None,
+                in_progress: false,
+                imported_from: None,
+            });
+            history.save()?;
+        } else {
+            // Missing pages may be the rest of the chapter; do not mark it read.
+            println!(
+                "Chapter {} is only partly cached ({} page(s)); not recording it in history.",
+                chapter.label,
+                chapter.pages.len()
+            );
+        }
+
+        if idx + 1 < labels.len() {
+            default_idx = idx + 1;
+        } else {
+            println!("No further cached chapters. Exiting.");
+            return Ok(());
+        }
+    }
+}
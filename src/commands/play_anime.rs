@@ -1,28 +1,61 @@
 use crate::{
     adapters::{
-        mal_client::{
-            AnimeInfo, MalClient, MalIdCache, SyncUpdate, WatchStatus, should_confirm_sync,
+        mpv_ipc::PlaybackProgress,
+        player::{DefaultPlayerGateway, PlayerExit, PlayerSession, SessionEvent},
+        player_backend::{PlayerBackend, select_backend},
+        providers::{
+            ProviderError, ProviderResult,
+            ports::AnimeProvider,
+            registry::{self, ProviderDescriptor},
         },
-        player::DefaultPlayerGateway,
-        providers::{allanime::AllAnimeClient, ports::AnimeProvider},
+        remote_id_cache::RemoteIdCache,
+        stream_policy::{order_streams, select_subtitles},
+        sync_backend::{AnimeInfo, SyncBackend, SyncUpdate, WatchStatus, should_confirm_sync},
+        sync_queue::{QueuedChange, discard_queued_update, queue_failed_update},
     },
     cli::Cli,
+    config::{AppConfig, StreamsConfig, SubtitlesConfig},
     history::{History, HistoryEntry},
     progression::{next_label_presorted, sorted_labels_numeric},
-    prompt::{confirm, rate, select_episode, select_show_entry},
-    types::{Provider, ShowInfo, Translation},
+    prompt::{confirm, format_timestamp, rate, select_episode, select_show_entry},
+    types::{Provider, ShowInfo, StreamOption, Translation},
 };
 
 use anyhow::{Result, bail};
 use chrono::Utc;
-use reqwest::StatusCode;
+use std::{future::Future, pin::Pin, sync::Arc};
+
+/// Per-session playback preferences resolved from config and CLI flags.
+#[derive(Debug, Clone)]
+pub struct PlaybackSettings {
+    pub player: Arc<dyn PlayerBackend>,
+    pub binge: bool,
+    /// Show the stream picker instead of auto-selecting by `streams` policy.
+    pub pick_stream: bool,
+    pub streams: StreamsConfig,
+    pub subtitles: SubtitlesConfig,
+    /// Percentage an episode must be played to count as watched.
+    pub watched_threshold: f64,
+}
+
+impl PlaybackSettings {
+    pub fn new(cli: &Cli, config: &AppConfig) -> Self {
+        Self {
+            player: select_backend(&config.player, config.player_backend),
+            binge: cli.binge || config.binge,
+            pick_stream: cli.pick_stream,
+            streams: config.streams.clone(),
+            subtitles: config.subtitles.clone(),
+            watched_threshold: f64::from(config.watched_threshold.min(100)),
+        }
+    }
+}
 
 pub async fn run_anime_command(
     cli: &Cli,
     history: &mut History,
-    player: &String,
-    mal_client: Option<&MalClient>,
-    binge: bool,
+    settings: &PlaybackSettings,
+    sync: &[Box<dyn SyncBackend>],
 ) -> Result<()> {
     let translation = if cli.dub {
         Translation::Dub
@@ -30,22 +63,19 @@ pub async fn run_anime_command(
         Translation::Sub
     };
 
-    if !matches!(cli.provider, Provider::Allanime) {
-        eprintln!("Warning: Only 'allanime' provider supports anime. Switching to 'allanime'.");
-    }
-
-    run_anime_flow(cli, history, translation, player, mal_client, binge).await
+    let provider = registry::anime_provider(cli.provider.provider());
+    run_anime_flow(cli, history, translation, provider, settings, sync).await
 }
 
 pub async fn run_anime_flow(
     cli: &Cli,
     history: &mut History,
     translation: Translation,
-    player: &String,
-    mal_client: Option<&MalClient>,
-    binge: bool,
+    provider: &ProviderDescriptor,
+    settings: &PlaybackSettings,
+    sync: &[Box<dyn SyncBackend>],
 ) -> Result<()> {
-    let client = AllAnimeClient::new()?;
+    let client = provider.anime_client(&settings.streams)?;
 
     if cli.query.is_empty() {
         println!("No query provided. Use `anv <name>` or `anv history`.");
@@ -65,30 +95,33 @@ pub async fn run_anime_flow(
 
     play_show(
         history,
-        &client,
+        client.as_ref(),
         translation,
-        Provider::Allanime,
+        provider.provider,
         show,
         cli.episode.clone(),
-        &player,
-        mal_client,
-        binge,
+        settings,
+        sync,
     )
     .await
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn play_show(
     history: &mut History,
-    client: &impl AnimeProvider,
+    client: &dyn AnimeProvider,
     translation: Translation,
     provider: Provider,
     show: &ShowInfo,
     prefer_episode: Option<String>,
-    player: &str,
-    mal_client: Option<&MalClient>,
-    binge: bool,
+    settings: &PlaybackSettings,
+    sync: &[Box<dyn SyncBackend>],
 ) -> Result<()> {
     let player_gateway = DefaultPlayerGateway;
+    let binge = settings.binge;
+    // Queueing episodes needs mpv's IPC, and the stream picker needs a prompt
+    // per episode, so both keep one player per episode.
+    let mut shared_player = binge && !settings.pick_stream && settings.player.supports_ipc();
     let episodes = client.fetch_episodes(&show.id, translation).await?;
     if episodes.is_empty() {
         bail!(
@@ -129,13 +162,23 @@ pub(crate) async fn play_show(
         None => (fallback, false),
     };
 
-    let mut mal_id_cache = if mal_client.is_some() {
-        MalIdCache::load().unwrap_or_else(|err| {
-            eprintln!("[sync] Warning: could not load ID cache ({err}), starting fresh.");
-            MalIdCache::default()
+    let sync = sync
+        .iter()
+        .map(|backend| SyncTarget {
+            backend: backend.as_ref(),
+            ids: RemoteIdCache::load_for(backend.service()).unwrap_or_else(|err| {
+                eprintln!("[sync] Warning: could not load ID cache ({err}), starting fresh.");
+                RemoteIdCache::default()
+            }),
         })
-    } else {
-        MalIdCache::default()
+        .collect();
+    let mut session = ShowSession {
+        client,
+        translation,
+        provider,
+        show,
+        settings,
+        sync,
     };
 
     loop {
@@ -167,17 +210,15 @@ pub(crate) async fn play_show(
         println!("Fetching streams for episode {}...", chosen);
         let streams = match client.fetch_streams(&show.id, translation, &chosen).await {
             Ok(streams) => streams,
+            Err(ProviderError::EpisodeNotYetAvailable { .. }) => {
+                eprintln!(
+                    "Episode {chosen} is not yet available for {} translation.",
+                    translation.label()
+                );
+                current_episode = latest_available.clone();
+                continue;
+            }
             Err(err) => {
-                if let Some(req_err) = err.downcast_ref::<reqwest::Error>() {
-                    if req_err.status() == Some(StatusCode::BAD_REQUEST) {
-                        eprintln!(
-                            "Episode {chosen} is not yet available for {} translation.",
-                            translation.label()
-                        );
-                        current_episode = latest_available.clone();
-                        continue;
-                    }
-                }
                 eprintln!("Error fetching streams: {}", err);
                 continue;
             }
@@ -191,149 +232,471 @@ pub(crate) async fn play_show(
             continue;
         }
 
-        let Some(stream) = player_gateway.choose_stream(streams)? else {
-            continue;
-        };
+        let mut candidates = rank_streams(streams, settings);
+
+        if shared_player {
+            match binge_session(
+                &mut session,
+                history,
+                &sorted_episodes,
+                &chosen,
+                &candidates,
+            )
+            .await?
+            {
+                BingeExit::Done => return Ok(()),
+                BingeExit::Fallback(episode) => {
+                    shared_player = false;
+                    current_episode = episode;
+                    continue;
+                }
+            }
+        }
+
+        if settings.pick_stream {
+            let Some(idx) = player_gateway.choose_stream_index(&candidates)? else {
+                continue;
+            };
+            let picked = candidates.remove(idx);
+            candidates.insert(0, picked);
+        }
 
         let next_candidate = next_label_presorted(&chosen, &sorted_episodes);
+        let resume_at = announce_resume(history, &session, &chosen);
 
-        player_gateway
-            .launch_player(&stream, &show.title, &chosen, player)
-            .await?;
-
-        history.upsert(HistoryEntry {
-            show_id: show.id.clone(),
-            show_title: show.title.clone(),
-            episode: chosen.clone(),
-            translation,
-            provider,
-            is_manga: false,
-            watched_at: Utc::now(),
-        });
-        history.save()?;
+        let mut played = None;
+        for (attempt, stream) in candidates.iter().enumerate() {
+            if attempt == 0 {
+                println!("Playing {}", stream.label());
+            } else {
+                println!("Retrying with {}...", stream.label());
+            }
+            let outcome = player_gateway
+                .launch_player(stream, &show.title, &chosen, &settings.player, resume_at)
+                .await?;
+            match outcome.exit {
+                PlayerExit::Finished => {
+                    played = Some(outcome);
+                    break;
+                }
+                PlayerExit::StreamFailed => {
+                    eprintln!("Stream {} could not be played.", stream.label());
+                }
+            }
+        }
+        let Some(outcome) = played else {
+            bail!(
+                "All {} stream(s) failed for episode {chosen}.",
+                candidates.len()
+            );
+        };
+
+        if !session
+            .record_episode(history, &chosen, outcome.progress)
+            .await?
+        {
+            if binge {
+                println!("Exiting playback loop.");
+                return Ok(());
+            }
+            current_episode = chosen;
+            continue;
+        }
 
-        if let Some(mal) = mal_client {
-            let ep_num = chosen.parse::<u32>().unwrap_or(0);
+        match (auto_advance || binge, next_candidate) {
+            (true, Some(next)) => current_episode = next,
+            (true, None) => {
+                println!("No further episodes found. Exiting.");
+                return Ok(());
+            }
+            (false, candidate) => current_episode = candidate.unwrap_or(chosen),
+        }
+    }
+}
 
-            let mal_id_opt = if let Some(cached_id) = mal_id_cache.get(&show.id) {
-                Some(cached_id)
-            } else {
-                match mal.resolve_and_confirm_mal_id(&show.title).await {
-                    Ok(Some(id)) => {
-                        if let Err(err) = mal_id_cache.insert_and_save(&show.id, id) {
-                            eprintln!("[sync] Warning: could not save ID cache: {err}");
+/// The show being played and everything that stays fixed across its episodes.
+struct ShowSession<'a> {
+    client: &'a dyn AnimeProvider,
+    translation: Translation,
+    provider: Provider,
+    show: &'a ShowInfo,
+    settings: &'a PlaybackSettings,
+    sync: Vec<SyncTarget<'a>>,
+}
+
+/// A list service the show's progress is pushed to, with the show → remote
+/// ID mappings already resolved for it.
+struct SyncTarget<'a> {
+    backend: &'a dyn SyncBackend,
+    ids: RemoteIdCache,
+}
+
+/// How a shared-player binge session ended.
+enum BingeExit {
+    /// The user stopped watching or the show ran out of episodes.
+    Done,
+    /// The shared player could not go on; continue from this episode with
+    /// one player per episode.
+    Fallback(String),
+}
+
+/// Plays `first` and the episodes after it in a single mpv instance. While
+/// one episode plays, the next one's streams are fetched and appended to the
+/// playlist; history and sync are updated whenever the playlist moves on.
+async fn binge_session<'a>(
+    session: &mut ShowSession<'a>,
+    history: &mut History,
+    sorted_episodes: &[String],
+    first: &str,
+    candidates: &[StreamOption],
+) -> Result<BingeExit> {
+    let client = session.client;
+    let show = session.show;
+    let translation = session.translation;
+    let settings = session.settings;
+
+    let stream = &candidates[0];
+    let resume_at = announce_resume(history, session, first);
+    println!("Playing {}", stream.label());
+    let mut player = PlayerSession::start(stream, &show.title, first, &settings.player, resume_at)?;
+
+    // Episode label and subtitles of every playlist entry, in playlist order.
+    let mut playlist = vec![(first.to_string(), stream.subtitles.clone())];
+    let mut current = 0;
+    let mut progress = None;
+    let mut prefetch: Option<Pin<Box<dyn Future<Output = PrefetchedStreams> + 'a>>> = None;
+    let mut prefetched_after = None;
+
+    loop {
+        if prefetch.is_none() && playlist.len() == current + 1 && prefetched_after != Some(current)
+        {
+            prefetched_after = Some(current);
+            if let Some(next) = next_label_presorted(&playlist[current].0, sorted_episodes) {
+                prefetch = Some(Box::pin(prefetch_streams(
+                    client,
+                    show.id.clone(),
+                    translation,
+                    next,
+                )));
+            }
+        }
+
+        tokio::select! {
+            event = player.next_event() => match event? {
+                SessionEvent::Progress(state) => {
+                    let advanced = state
+                        .playlist_pos
+                        .filter(|pos| *pos > current && *pos < playlist.len());
+                    if let Some(pos) = advanced {
+                        let finished = playlist[current].0.clone();
+                        session.record_episode(history, &finished, progress).await?;
+                        current = pos;
+                        println!("Playing episode {}", playlist[current].0);
+                        let subtitles = &playlist[current].1;
+                        if !subtitles.is_empty() {
+                            if let Err(err) = player.add_subtitles(subtitles).await {
+                                eprintln!("Could not load subtitles: {err}");
+                            }
                         }
-                        Some(id)
                     }
-                    Ok(None) => None,
+                    if advanced.is_some() || state.progress.is_some() {
+                        progress = state.progress;
+                    }
+                }
+                SessionEvent::Exited(exit) => {
+                    let episode = playlist[current].0.clone();
+                    if exit == PlayerExit::StreamFailed && progress.is_none() {
+                        eprintln!("Episode {episode} could not be played.");
+                        return Ok(BingeExit::Fallback(episode));
+                    }
+                    if !session.record_episode(history, &episode, progress).await? {
+                        println!("Exiting playback loop.");
+                        return Ok(BingeExit::Done);
+                    }
+                    let queued_next = playlist.len() > current + 1;
+                    return Ok(match next_label_presorted(&episode, sorted_episodes) {
+                        // The queued episode failed, or nothing was queued in time.
+                        Some(next) if exit == PlayerExit::StreamFailed || !queued_next => {
+                            BingeExit::Fallback(next)
+                        }
+                        Some(_) => BingeExit::Done,
+                        None => {
+                            println!("No further episodes found. Exiting.");
+                            BingeExit::Done
+                        }
+                    });
+                }
+            },
+            (episode, streams) = async { prefetch.as_mut().expect("guarded by precondition").await },
+                if prefetch.is_some() =>
+            {
+                prefetch = None;
+                let streams = match streams {
+                    Ok(streams) if !streams.is_empty() => streams,
+                    Ok(_) => {
+                        eprintln!("No supported streams found for episode {episode}.");
+                        continue;
+                    }
                     Err(err) => {
-                        eprintln!("[sync] MAL ID resolution failed: {err}");
-                        None
+                        eprintln!("Could not fetch streams for episode {episode}: {err}");
+                        continue;
                     }
+                };
+                let Some(stream) = rank_streams(streams, settings).into_iter().next() else {
+                    continue;
+                };
+                let start = history.resume_position(&show.id, translation, &episode);
+                match player.append(&stream, &show.title, &episode, start).await {
+                    Ok(()) => playlist.push((episode, stream.subtitles.clone())),
+                    Err(err) => eprintln!("Could not queue episode {episode}: {err}"),
                 }
-            };
+            }
+        }
+    }
+}
 
-            if let Some(mal_id) = mal_id_opt {
-                let anime_info = mal.get_anime_info(mal_id).await.unwrap_or_else(|err| {
-                    eprintln!(
-                        "[sync] Warning: could not fetch anime info ({err}), assuming Watching."
-                    );
-                    AnimeInfo {
-                        list_status: None,
-                        num_episodes: 0,
-                    }
-                });
-                let current = anime_info.list_status;
+/// Orders streams by the `[streams]` policy and keeps only the subtitle
+/// tracks in the configured languages.
+fn rank_streams(streams: Vec<StreamOption>, settings: &PlaybackSettings) -> Vec<StreamOption> {
+    let mut ranked = order_streams(streams, &settings.streams);
+    for stream in &mut ranked {
+        stream.subtitles = select_subtitles(&stream.subtitles, &settings.subtitles);
+    }
+    ranked
+}
 
-                let new_status = if anime_info.num_episodes > 0 && ep_num >= anime_info.num_episodes
-                {
-                    WatchStatus::Completed
-                } else {
-                    WatchStatus::Watching
-                };
+type PrefetchedStreams = (String, ProviderResult<Vec<StreamOption>>);
 
-                let needs_confirm = should_confirm_sync(&current, new_status);
-
-                let should_update = confirm(&format!(
-                    "[sync] Update MAL: \"{}\" ep {} → {}?",
-                    show.title,
-                    ep_num,
-                    new_status.label()
-                ))?;
-
-                if should_update {
-                    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
-                    let is_first_start = new_status == WatchStatus::Watching
-                        && match &current {
-                            None => true,
-                            Some(cur) => cur.status == "plan_to_watch",
-                        };
-                    let start_date = if is_first_start {
-                        Some(today.clone())
-                    } else {
-                        None
-                    };
-                    let finish_date = if new_status == WatchStatus::Completed {
-                        Some(today)
-                    } else {
-                        None
-                    };
+async fn prefetch_streams(
+    client: &dyn AnimeProvider,
+    show_id: String,
+    translation: Translation,
+    episode: String,
+) -> PrefetchedStreams {
+    let streams = client.fetch_streams(&show_id, translation, &episode).await;
+    (episode, streams)
+}
 
-                    let score: Option<u8> = if new_status == WatchStatus::Completed {
-                        let rating_idx = rate(&show.title)?;
+/// Looks up where `episode` was left off and tells the user playback resumes there.
+fn announce_resume(history: &History, session: &ShowSession<'_>, episode: &str) -> Option<f64> {
+    let resume_at = history.resume_position(&session.show.id, session.translation, episode);
+    if let Some(pos) = resume_at {
+        println!("Resuming episode {episode} at {}.", format_timestamp(pos));
+    }
+    resume_at
+}
 
-                        match rating_idx {
-                            Some(idx) if idx < 10 => Some(idx as u8 + 1),
-                            _ => None,
-                        }
-                    } else {
-                        None
-                    };
-
-                    let update = SyncUpdate {
-                        title: show.title.clone(),
-                        episode: ep_num,
-                        total_episodes: if anime_info.num_episodes > 0 {
-                            Some(anime_info.num_episodes)
-                        } else {
-                            None
-                        },
-                        status: new_status,
-                        start_date,
-                        finish_date,
-                        score,
-                    };
-                    match mal.update_status_with_id(mal_id, &update).await {
-                        Ok(()) => {
-                            if needs_confirm {
-                                println!(
-                                    "[sync] MAL updated: ep {} → {}",
-                                    ep_num,
-                                    new_status.label()
-                                );
-                            } else {
-                                println!("[sync] MAL progress saved: ep {}", ep_num);
-                            }
-                            if let Some(score_val) = score {
-                                println!("[sync] MAL score submitted: {}/10", score_val);
-                            } else if new_status == WatchStatus::Completed {
-                                println!("[sync] Rating skipped.");
-                            }
-                        }
-                        Err(err) => eprintln!("[sync] Failed to update MAL: {err}"),
+/// Saves how far `episode` of `show` was played and returns whether it counts
+/// as watched under `settings.watched_threshold`.
+pub(crate) fn record_playback(
+    history: &mut History,
+    settings: &PlaybackSettings,
+    show: &ShowInfo,
+    translation: Translation,
+    provider: Provider,
+    episode: &str,
+    progress: Option<PlaybackProgress>,
+) -> Result<bool> {
+    // Without position tracking (IPC unavailable) every exit counts as watched.
+    let watched_percent = progress.and_then(|p| p.watched_percent());
+    let threshold = settings.watched_threshold;
+    let watched = watched_percent.is_none_or(|pct| pct >= threshold);
+
+    history.upsert(HistoryEntry {
+        show_id: show.id.clone(),
+        show_title: show.title.clone(),
+        episode: episode.to_string(),
+        translation,
+        provider,
+        is_manga: false,
+        watched_at: Utc::now(),
+        position: progress.and_then(|p| p.resume_position()),
+        duration: progress.and_then(|p| p.duration),
+        in_progress: !watched,
+        imported_from: None,
+    });
+    history.save()?;
+
+    if !watched {
+        println!(
+            "Episode {episode} stopped at {:.0}% (below {threshold:.0}%); keeping it in progress.",
+            watched_percent.unwrap_or(0.0),
+        );
+    }
+    Ok(watched)
+}
+
+impl ShowSession<'_> {
+    /// Saves how far `episode` was played and, once it counts as watched,
+    /// syncs it to the list service. Returns whether the episode counts as watched.
+    async fn record_episode(
+        &mut self,
+        history: &mut History,
+        episode: &str,
+        progress: Option<PlaybackProgress>,
+    ) -> Result<bool> {
+        let watched = record_playback(
+            history,
+            self.settings,
+            self.show,
+            self.translation,
+            self.provider,
+            episode,
+            progress,
+        )?;
+        if !watched {
+            return Ok(false);
+        }
+
+        // Every service gets the same rating, so it is only asked for once.
+        let rating_label = self
+            .sync
+            .iter()
+            .map(|target| target.backend.service().label())
+            .collect::<Vec<_>>()
+            .join(" and ");
+        let mut rating = None;
+        for target in &mut self.sync {
+            target
+                .sync_progress(
+                    self.provider,
+                    self.show,
+                    episode,
+                    &rating_label,
+                    &mut rating,
+                )
+                .await?;
+        }
+        Ok(true)
+    }
+}
+
+impl SyncTarget<'_> {
+    /// Pushes `episode` of `show` to this service. `rating` caches the score
+    /// once it has been asked for under `rating_label`.
+    async fn sync_progress(
+        &mut self,
+        provider: Provider,
+        show: &ShowInfo,
+        episode: &str,
+        rating_label: &str,
+        rating: &mut Option<Option<u8>>,
+    ) -> Result<()> {
+        let sync = self.backend;
+        let service = sync.service().label();
+        let ep_num = episode.parse::<u32>().unwrap_or(0);
+
+        let remote_id_opt = if let Some(cached_id) = self.ids.get(&show.id) {
+            Some(cached_id)
+        } else {
+            match sync.resolve_anime_id(&show.title).await {
+                Ok(Some(id)) => {
+                    if let Err(err) = self.ids.insert_and_save(provider, &show.id, id) {
+                        eprintln!("[sync] Warning: could not save ID cache: {err}");
                     }
-                } else {
-                    println!("[sync] Skipped MAL update.");
+                    Some(id)
+                }
+                Ok(None) => None,
+                Err(err) => {
+                    eprintln!("[sync] {service} ID resolution failed: {err}");
+                    None
                 }
             }
         };
-        match (auto_advance || binge, next_candidate) {
-            (true, Some(next)) => current_episode = next,
-            (true, None) => {
-                println!("No further episodes found. Exiting.");
-                return Ok(());
+
+        let Some(remote_id) = remote_id_opt else {
+            return Ok(());
+        };
+        let anime_info = sync.anime_info(remote_id).await.unwrap_or_else(|err| {
+            eprintln!("[sync] Warning: could not fetch anime info ({err}), assuming Watching.");
+            AnimeInfo {
+                list_status: None,
+                num_episodes: 0,
             }
-            (false, candidate) => current_episode = candidate.unwrap_or(chosen),
+        });
+        let current = anime_info.list_status;
+
+        let new_status = if anime_info.num_episodes > 0 && ep_num >= anime_info.num_episodes {
+            WatchStatus::Completed
+        } else {
+            WatchStatus::Watching
+        };
+
+        let needs_confirm = should_confirm_sync(&current, new_status);
+
+        let should_update = confirm(&format!(
+            "[sync] Update {service}: \"{}\" ep {} → {}?",
+            show.title,
+            ep_num,
+            new_status.label()
+        ))?;
+
+        if should_update {
+            let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+            let is_first_start = new_status == WatchStatus::Watching
+                && match &current {
+                    None => true,
+                    Some(cur) => cur.status == "plan_to_watch",
+                };
+            let start_date = if is_first_start {
+                Some(today.clone())
+            } else {
+                None
+            };
+            let finish_date = if new_status == WatchStatus::Completed {
+                Some(today)
+            } else {
+                None
+            };
+
+            let score = if new_status == WatchStatus::Completed {
+                match *rating {
+                    Some(score) => score,
+                    None => *rating.insert(rate(&show.title, rating_label)?),
+                }
+            } else {
+                None
+            };
+
+            let update = SyncUpdate {
+                title: show.title.clone(),
+                episode: ep_num,
+                total_episodes: if anime_info.num_episodes > 0 {
+                    Some(anime_info.num_episodes)
+                } else {
+                    None
+                },
+                status: new_status,
+                start_date,
+                finish_date,
+                score,
+            };
+            match sync.update_anime(remote_id, &update).await {
+                Ok(()) => {
+                    discard_queued_update(sync.service(), remote_id, false);
+                    if needs_confirm {
+                        println!(
+                            "[sync] {service} updated: ep {} → {}",
+                            ep_num,
+                            new_status.label()
+                        );
+                    } else {
+                        println!("[sync] {service} progress saved: ep {}", ep_num);
+                    }
+                    if let Some(score_val) = score {
+                        println!("[sync] {service} score submitted: {}/10", score_val);
+                    } else if new_status == WatchStatus::Completed {
+                        println!("[sync] Rating skipped.");
+                    }
+                }
+                Err(err) => {
+                    eprintln!("[sync] Failed to update {service}: {err}");
+                    queue_failed_update(sync.service(), remote_id, QueuedChange::Anime(update));
+                }
+            }
+        } else {
+            println!("[sync] Skipped {service} update.");
         }
+        Ok(())
     }
 }
@@ -0,0 +1,27 @@
+use crate::adapters::providers::registry::{self, PROVIDERS, ProviderDescriptor};
+
+use anyhow::{Result, bail};
+
+pub fn run_providers_command(name: Option<&str>) -> Result<()> {
+    let Some(name) = name else {
+        for provider in PROVIDERS {
+            print_provider(provider);
+        }
+        return Ok(());
+    };
+
+    match registry::find(name) {
+        Some(provider) => {
+            print_provider(provider);
+            Ok(())
+        }
+        None => {
+            let known: Vec<&str> = PROVIDERS.iter().map(|p| p.name).collect();
+            bail!("Unknown provider '{name}'. Available: {}", known.join(", "))
+        }
+    }
+}
+
+fn print_provider(provider: &ProviderDescriptor) {
+    println!("{:<10} {}", provider.name, provider.capabilities.summary());
+}
@@ -1,114 +1,409 @@
 use crate::{
     adapters::{
-        providers::{
-            allanime::AllAnimeClient, mangadex::MangaDexClient, mangapill::MangapillClient,
-            ports::MangaProvider,
-        },
+        player_backend::PlayerBackend,
+        providers::{ports::MangaProvider, registry},
         reader::{
-            cache::{MangaCacheState, cache_manga_pages},
+            cache::{MangaCacheState, cache_manga_pages, manga_cache_dir},
             reader::DefaultReaderGateway,
         },
+        remote_id_cache::RemoteIdCache,
+        sync_backend::{MangaListInfo, MangaSyncUpdate, ReadStatus, SyncBackend},
+        sync_queue::{QueuedChange, discard_queued_update, queue_failed_update},
     },
-    cli::Cli,
+    cli::{Cli, ProviderSelection},
+    config::MangaConfig,
     history::{History, HistoryEntry},
+    library::LibraryInfo,
     progression::{next_label_presorted, sorted_labels_numeric},
-    prompt::{select_episode, select_manga_entry},
-    types::{MangaInfo, Provider, Translation},
+    prompt::{confirm, rate, select_episode, select_manga_entry, select_manga_source},
+    types::{MangaInfo, MangaMatch, Provider, Translation, normalize_title},
 };
 
 use anyhow::{Result, bail};
 use chrono::Utc;
-use std::path::Path;
+use futures::{StreamExt, future::join_all, stream};
+use std::{path::Path, sync::Arc};
 
 const INITIAL_MANGA_PAGE_PRELOAD: usize = 5;
+/// Chapter counts looked up at the same time per provider when grouping
+/// `--provider all` results; kept low to stay under provider rate limits.
+const COUNT_CONCURRENCY: usize = 4;
 
-pub async fn run_manga_command(cli: &Cli, history: &mut History) -> Result<()> {
+/// Why a reading session ended.
+pub enum ReadOutcome {
+    Finished,
+    /// The provider stopped working; `chapter` is where the reader was.
+    ProviderFailed {
+        chapter: Option<String>,
+        reason: String,
+    },
+}
+
+pub async fn run_manga_command(
+    cli: &Cli,
+    history: &mut History,
+    manga_config: &MangaConfig,
+    player: &Arc<dyn PlayerBackend>,
+    sync: &[Box<dyn SyncBackend>],
+) -> Result<()> {
     let translation = if cli.raw {
         Translation::Raw
     } else {
         Translation::Sub
     };
 
-    match cli.provider {
-        Provider::Allanime => {
-            let client = AllAnimeClient::new()?;
-            run_manga_flow(cli, history, translation, &client).await
-        }
-        Provider::Mangadex => {
-            let client = MangaDexClient::new()?;
-            run_manga_flow(cli, history, translation, &client).await
-        }
-        Provider::Mangapill => {
-            let client = MangapillClient::new()?;
-            run_manga_flow(cli, history, translation, &client).await
-        }
-    }
-}
-
-pub async fn run_manga_flow(
-    cli: &Cli,
-    history: &mut History,
-    translation: Translation,
-    client: &impl MangaProvider,
-) -> Result<()> {
     if cli.query.is_empty() {
         println!("No query provided. Use `anv --manga <name>`.");
         return Ok(());
     }
 
     let query = cli.query.join(" ");
-    let mangas = client.search_mangas(&query, translation).await?;
-    if mangas.is_empty() {
-        bail!("No results for \"{}\" ({})", query, translation.label());
-    }
-
-    let Some(manga) = select_manga_entry(&mangas, translation)? else {
+    let Some((provider, manga)) =
+        search_and_select(cli.provider, &query, translation, manga_config).await?
+    else {
         println!("Cancelled.");
         return Ok(());
     };
-    read_manga(
+    read_with_fallback(
         history,
-        client,
         translation,
         manga,
+        provider,
         cli.episode.clone(),
         cli.cache_dir.as_deref(),
-        cli.provider,
+        manga_config,
+        player,
+        sync,
     )
     .await
 }
 
+/// Searches `selection` for `query` and lets the user pick a title and, when
+/// several providers have it, a source. `None` if the user cancelled.
+pub(crate) async fn search_and_select(
+    selection: ProviderSelection,
+    query: &str,
+    translation: Translation,
+    manga_config: &MangaConfig,
+) -> Result<Option<(Provider, MangaInfo)>> {
+    let matches = match selection {
+        ProviderSelection::All => search_all_providers(query, translation).await?,
+        ProviderSelection::One(provider) => {
+            search_with_fallback(provider, query, translation, manga_config).await?
+        }
+    };
+    if matches.is_empty() {
+        bail!("No results for \"{}\" ({})", query, translation.label());
+    }
+
+    let Some(selected) = select_manga_entry(&matches, translation)? else {
+        return Ok(None);
+    };
+    Ok(select_manga_source(selected, translation)?.cloned())
+}
+
+/// Searches `provider`, moving on to the configured fallbacks only if the
+/// search itself fails.
+async fn search_with_fallback(
+    provider: Provider,
+    query: &str,
+    translation: Translation,
+    manga_config: &MangaConfig,
+) -> Result<Vec<MangaMatch>> {
+    let selected = registry::descriptor(provider);
+    if !selected.capabilities.supports(translation) {
+        eprintln!(
+            "Warning: '{}' does not offer {} manga; results may be in another language.",
+            selected.name,
+            translation.label()
+        );
+    }
+
+    for provider in fallback_order(provider, manga_config, translation) {
+        let descriptor = registry::descriptor(provider);
+        let client = descriptor.manga_client()?;
+        match client.search_mangas(query, translation).await {
+            Ok(mangas) => {
+                return Ok(mangas
+                    .into_iter()
+                    .map(|manga| MangaMatch {
+                        title: manga.title.clone(),
+                        sources: vec![(provider, manga)],
+                    })
+                    .collect());
+            }
+            Err(err) => eprintln!("Search on {} failed ({err}).", descriptor.name),
+        }
+    }
+    bail!("Could not search for \"{query}\" on any provider.")
+}
+
+/// `--provider all`: searches every manga provider that serves `translation`
+/// concurrently and groups the results by title.
+async fn search_all_providers(query: &str, translation: Translation) -> Result<Vec<MangaMatch>> {
+    let descriptors: Vec<_> = registry::PROVIDERS
+        .iter()
+        .filter(|d| d.capabilities.manga && d.capabilities.supports(translation))
+        .collect();
+    let searches = descriptors.iter().map(|descriptor| async move {
+        let client = descriptor.manga_client()?;
+        Ok::<_, anyhow::Error>(client.search_mangas(query, translation).await?)
+    });
+    let outcomes = join_all(searches).await;
+
+    let mut results = Vec::new();
+    for (descriptor, outcome) in descriptors.iter().zip(outcomes) {
+        match outcome {
+            Ok(mangas) => results.push((descriptor.provider, mangas)),
+            Err(err) => eprintln!("Search on {} failed ({err}).", descriptor.name),
+        }
+    }
+    if results.is_empty() {
+        bail!("Could not search for \"{query}\" on any provider.");
+    }
+    let lookups = results
+        .iter_mut()
+        .map(|(provider, mangas)| fill_chapter_counts(*provider, mangas, translation));
+    join_all(lookups).await;
+    Ok(group_by_title(results))
+}
+
+/// Looks up the chapter counts a search left at zero so grouped results can
+/// be compared across providers. A failed lookup leaves the count at zero.
+async fn fill_chapter_counts(
+    provider: Provider,
+    mangas: &mut [MangaInfo],
+    translation: Translation,
+) {
+    let Ok(client) = registry::descriptor(provider).manga_client() else {
+        return;
+    };
+    let client = client.as_ref();
+    let pending: Vec<&mut MangaInfo> = mangas
+        .iter_mut()
+        .filter(|manga| chapters_for(manga, translation) == 0)
+        .collect();
+    stream::iter(pending)
+        .for_each_concurrent(COUNT_CONCURRENCY, |manga| async move {
+            if let Ok(count) = client.chapter_count(&manga.id, translation).await {
+                match translation {
+                    Translation::Raw => manga.available_chapters.raw = count,
+                    _ => manga.available_chapters.sub = count,
+                }
+            }
+        })
+        .await;
+}
+
+fn chapters_for(manga: &MangaInfo, translation: Translation) -> usize {
+    match translation {
+        Translation::Raw => manga.available_chapters.raw,
+        _ => manga.available_chapters.sub,
+    }
+}
+
+/// Merges per-provider results whose normalized titles match. A provider
+/// contributes at most one entry per group; a second hit with the same title
+/// starts a new group.
+fn group_by_title(results: Vec<(Provider, Vec<MangaInfo>)>) -> Vec<MangaMatch> {
+    let mut groups: Vec<(String, MangaMatch)> = Vec::new();
+    for (provider, mangas) in results {
+        for manga in mangas {
+            let key = normalize_title(&manga.title);
+            let existing = groups
+                .iter_mut()
+                .find(|(k, group)| *k == key && !group.sources.iter().any(|(p, _)| *p == provider));
+            match existing {
+                Some((_, group)) => group.sources.push((provider, manga)),
+                None => groups.push((
+                    key,
+                    MangaMatch {
+                        title: manga.title.clone(),
+                        sources: vec![(provider, manga)],
+                    },
+                )),
+            }
+        }
+    }
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+/// Reads `manga` on `provider`, switching to the next configured provider
+/// that has the same title whenever the current one fails, and picking up at
+/// the chapter the reader was on.
+#[allow(clippy::too_many_arguments)]
+pub async fn read_with_fallback(
+    history: &mut History,
+    translation: Translation,
+    mut manga: MangaInfo,
+    mut provider: Provider,
+    mut chapter: Option<String>,
+    cache_base_override: Option<&Path>,
+    manga_config: &MangaConfig,
+    player: &Arc<dyn PlayerBackend>,
+    sync: &[Box<dyn SyncBackend>],
+) -> Result<()> {
+    let mut sync = MangaSync::new(sync);
+    let mut remaining = fallback_order(provider, manga_config, translation)
+        .into_iter()
+        .skip(1);
+    loop {
+        let descriptor = registry::descriptor(provider);
+        let client = descriptor.manga_client()?;
+        let outcome = read_manga(
+            history,
+            client.as_ref(),
+            translation,
+            &manga,
+            chapter.clone(),
+            cache_base_override,
+            provider,
+            player,
+            &mut sync,
+        )
+        .await?;
+        let ReadOutcome::ProviderFailed {
+            chapter: failed_at,
+            reason,
+        } = outcome
+        else {
+            return Ok(());
+        };
+
+        eprintln!("{} failed: {reason}", descriptor.name);
+        chapter = failed_at.or(chapter);
+        let Some((next, found)) =
+            find_on_providers(&manga.title, translation, &mut remaining).await
+        else {
+            bail!(
+                "\"{}\" is unavailable on {} and no other provider has it.",
+                manga.title,
+                descriptor.name
+            );
+        };
+        match &chapter {
+            Some(label) => println!(
+                "Continuing \"{}\" on {} at chapter {label}.",
+                found.title,
+                registry::descriptor(next).name
+            ),
+            None => println!(
+                "Continuing \"{}\" on {}.",
+                found.title,
+                registry::descriptor(next).name
+            ),
+        }
+        provider = next;
+        manga = found;
+    }
+}
+
+/// `first`, then the configured fallback providers that can serve `translation`.
+fn fallback_order(
+    first: Provider,
+    manga_config: &MangaConfig,
+    translation: Translation,
+) -> Vec<Provider> {
+    let mut order = vec![first];
+    for &provider in &manga_config.fallback_providers {
+        let capabilities = registry::descriptor(provider).capabilities;
+        if !order.contains(&provider) && capabilities.manga && capabilities.supports(translation) {
+            order.push(provider);
+        }
+    }
+    order
+}
+
+/// Searches each provider for `title` and returns the first confident match.
+async fn find_on_providers(
+    title: &str,
+    translation: Translation,
+    providers: &mut impl Iterator<Item = Provider>,
+) -> Option<(Provider, MangaInfo)> {
+    for provider in providers {
+        let descriptor = registry::descriptor(provider);
+        let Ok(client) = descriptor.manga_client() else {
+            continue;
+        };
+        match client.search_mangas(title, translation).await {
+            Ok(results) => match match_title(title, results) {
+                Some(found) => return Some((provider, found)),
+                None => eprintln!("\"{title}\" not found on {}.", descriptor.name),
+            },
+            Err(err) => eprintln!("Search on {} failed ({err}).", descriptor.name),
+        }
+    }
+    None
+}
+
+/// An exact match on the normalized title, or else the only result whose
+/// title contains (or is contained in) the wanted one.
+fn match_title(title: &str, results: Vec<MangaInfo>) -> Option<MangaInfo> {
+    let wanted = normalize_title(title);
+    let normalized: Vec<String> = results.iter().map(|m| normalize_title(&m.title)).collect();
+    if let Some(idx) = normalized.iter().position(|t| *t == wanted) {
+        return results.into_iter().nth(idx);
+    }
+    let mut partial = normalized
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| !t.is_empty() && (t.contains(&wanted) || wanted.contains(t.as_str())))
+        .map(|(idx, _)| idx);
+    match (partial.next(), partial.next()) {
+        (Some(idx), None) => results.into_iter().nth(idx),
+        _ => None,
+    }
+}
+
+/// Tags the manga's page cache directory so `--offline` can list it by title.
+pub(crate) fn record_in_library(
+    manga: &MangaInfo,
+    provider: Provider,
+    cache_base_override: Option<&Path>,
+) {
+    let info = LibraryInfo {
+        id: manga.id.clone(),
+        title: manga.title.clone(),
+        provider,
+        translation: None,
+    };
+    let written = manga_cache_dir(&manga.id, cache_base_override).and_then(|dir| info.write(&dir));
+    if let Err(err) = written {
+        eprintln!("Warning: could not record manga in the library: {err:#}");
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn read_manga(
     history: &mut History,
-    client: &impl MangaProvider,
+    client: &dyn MangaProvider,
     translation: Translation,
     manga: &MangaInfo,
     prefer_chapter: Option<String>,
     cache_base_override: Option<&Path>,
     provider: Provider,
-) -> Result<()> {
+    player: &Arc<dyn PlayerBackend>,
+    sync: &mut MangaSync<'_>,
+) -> Result<ReadOutcome> {
     let reader_gateway = DefaultReaderGateway;
     let chapters = match client.fetch_chapters(&manga.id, translation).await {
         Ok(c) => c,
         Err(err) => {
-            let msg = err.to_string();
-            if msg.contains("connection closed")
-                || msg.contains("SendRequest")
-                || msg.contains("connect")
-            {
-                bail!(
-                    "Could not connect to the provider — your network may be blocking it.\nTry a different provider: --provider mangadex  or  --provider mangapill"
-                );
-            }
-            return Err(err);
+            return Ok(ReadOutcome::ProviderFailed {
+                chapter: prefer_chapter,
+                reason: format!("could not fetch chapters ({err})"),
+            });
         }
     };
     if chapters.is_empty() {
-        bail!(
-            "No {} chapters available for {}",
-            translation.label(),
-            manga.title
-        );
+        return Ok(ReadOutcome::ProviderFailed {
+            chapter: prefer_chapter,
+            reason: format!(
+                "no {} chapters available for {}",
+                translation.label(),
+                manga.title
+            ),
+        });
     }
 
     let chapter_labels: Vec<String> = chapters.iter().map(|c| c.label.clone()).collect();
@@ -117,7 +412,7 @@ pub async fn read_manga(
     let latest_available = sorted_labels
         .last()
         .cloned()
-        .expect("chapters is non-empty; bail!() above ensures this");
+        .expect("chapters is non-empty; the early return above ensures this");
     println!(
         "Found {} {} chapters. Latest available: {}.",
         chapters.len(),
@@ -161,7 +456,7 @@ pub async fn read_manga(
             )?
             else {
                 println!("Exiting reading loop.");
-                return Ok(());
+                return Ok(ReadOutcome::Finished);
             };
             i
         };
@@ -176,11 +471,10 @@ pub async fn read_manga(
         {
             Ok(pages) => pages,
             Err(err) => {
-                eprintln!(
-                    "Failed to fetch pages for chapter {}: {}",
-                    chosen_label, err
-                );
-                continue;
+                return Ok(ReadOutcome::ProviderFailed {
+                    chapter: Some(chosen_label),
+                    reason: format!("could not fetch pages ({err})"),
+                });
             }
         };
 
@@ -214,6 +508,7 @@ pub async fn read_manga(
                         println!("Continuing to cache remaining pages in background...");
                     }
                 }
+                record_in_library(manga, provider, cache_base_override);
                 state
             }
             Err(err) => {
@@ -230,12 +525,10 @@ pub async fn read_manga(
         };
 
         if cache_state.cdn_blocked {
-            if auto_advance {
-                if let Some(next) = next_candidate {
-                    current_label = next;
-                }
-            }
-            continue;
+            return Ok(ReadOutcome::ProviderFailed {
+                chapter: Some(chosen_label),
+                reason: "its image CDN is blocked".to_string(),
+            });
         }
 
         reader_gateway
@@ -245,6 +538,7 @@ pub async fn read_manga(
                 &cache_state.cache_files,
                 &manga.title,
                 &chosen_label,
+                player,
             )
             .await?;
 
@@ -256,16 +550,164 @@ pub async fn read_manga(
             provider,
             is_manga: true,
             watched_at: Utc::now(),
+            position: None,
+            duration: None,
+            in_progress: false,
+            imported_from: None,
         });
         history.save()?;
+        sync.record_chapter(provider, manga, &chosen_label).await;
 
         match (auto_advance, next_candidate) {
             (true, Some(next)) => current_label = next,
             (true, None) => {
                 println!("No further chapters found. Exiting.");
-                return Ok(());
+                return Ok(ReadOutcome::Finished);
             }
             (false, candidate) => current_label = candidate.unwrap_or(chosen_label),
         }
     }
 }
+
+/// The list services that track manga, each with its manga → remote ID cache.
+pub struct MangaSync<'a> {
+    targets: Vec<(&'a dyn SyncBackend, RemoteIdCache)>,
+}
+
+impl<'a> MangaSync<'a> {
+    pub fn new(backends: &'a [Box<dyn SyncBackend>]) -> Self {
+        let targets = backends
+            .iter()
+            .filter(|backend| backend.supports_manga())
+            .map(|backend| {
+                let ids = RemoteIdCache::load_manga_for(backend.service()).unwrap_or_else(|err| {
+                    eprintln!("[sync] Warning: could not load ID cache ({err}), starting fresh.");
+                    RemoteIdCache::default()
+                });
+                (backend.as_ref(), ids)
+            })
+            .collect();
+        Self { targets }
+    }
+
+    /// Pushes `chapter` of `manga` to every target. Failures are reported and
+    /// never interrupt reading.
+    async fn record_chapter(&mut self, provider: Provider, manga: &MangaInfo, chapter: &str) {
+        // Chapter 12.5 means 12 full chapters read.
+        let Some(chapter_num) = chapter.parse::<f64>().ok().map(|n| n.floor() as u32) else {
+            return;
+        };
+        let mut rating = None;
+        for (backend, ids) in &mut self.targets {
+            if let Err(err) =
+                sync_chapter(*backend, ids, provider, manga, chapter_num, &mut rating).await
+            {
+                eprintln!(
+                    "[sync] Failed to update {}: {err}",
+                    backend.service().label()
+                );
+            }
+        }
+    }
+}
+
+async fn sync_chapter(
+    sync: &dyn SyncBackend,
+    ids: &mut RemoteIdCache,
+    provider: Provider,
+    manga: &MangaInfo,
+    chapter: u32,
+    rating: &mut Option<Option<u8>>,
+) -> Result<()> {
+    let service = sync.service().label();
+    let remote_id = match ids.get(&manga.id) {
+        Some(id) => id,
+        None => {
+            let Some(id) = sync.resolve_manga_id(&manga.title).await? else {
+                return Ok(());
+            };
+            if let Err(err) = ids.insert_and_save(provider, &manga.id, id) {
+                eprintln!("[sync] Warning: could not save ID cache: {err}");
+            }
+            id
+        }
+    };
+
+    let info = sync.manga_info(remote_id).await.unwrap_or_else(|err| {
+        eprintln!("[sync] Warning: could not fetch manga info ({err}), assuming Reading.");
+        MangaListInfo {
+            list_status: None,
+            num_chapters: 0,
+        }
+    });
+    let current = info.list_status;
+
+    let new_status = if info.num_chapters > 0 && chapter >= info.num_chapters {
+        ReadStatus::Completed
+    } else {
+        ReadStatus::Reading
+    };
+    let status_changes = current
+        .as_ref()
+        .is_none_or(|cur| cur.status != new_status.as_str());
+    // Re-reading an earlier chapter must not move progress backwards.
+    if !status_changes
+        && current
+            .as_ref()
+            .is_some_and(|cur| cur.num_chapters_read >= chapter)
+    {
+        return Ok(());
+    }
+
+    if status_changes
+        && !confirm(&format!(
+            "[sync] Update {service}: \"{}\" ch {chapter} → {}?",
+            manga.title,
+            new_status.label()
+        ))?
+    {
+        println!("[sync] Skipped {service} update.");
+        return Ok(());
+    }
+
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let is_first_start = new_status == ReadStatus::Reading
+        && current
+            .as_ref()
+            .is_none_or(|cur| cur.status == ReadStatus::PlanToRead.as_str());
+    let score = if new_status == ReadStatus::Completed {
+        match *rating {
+            Some(score) => score,
+            None => *rating.insert(rate(&manga.title, service)?),
+        }
+    } else {
+        None
+    };
+    let update = MangaSyncUpdate {
+        title: manga.title.clone(),
+        chapter,
+        status: new_status,
+        start_date: is_first_start.then(|| today.clone()),
+        finish_date: (new_status == ReadStatus::Completed).then_some(today),
+        score,
+    };
+    if let Err(err) = sync.update_manga(remote_id, &update).await {
+        eprintln!("[sync] Failed to update {service}: {err}");
+        queue_failed_update(sync.service(), remote_id, QueuedChange::Manga(update));
+        return Ok(());
+    }
+    discard_queued_update(sync.service(), remote_id, true);
+
+    if status_changes {
+        println!(
+            "[sync] {service} updated: ch {chapter} → {}",
+            new_status.label()
+        );
+    } else {
+        println!("[sync] {service} progress saved: ch {chapter}");
+    }
+    if let Some(score) = score {
+        println!("[sync] {service} score submitted: {score}/10");
+    }
+    Ok(())
+}
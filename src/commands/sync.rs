@@ -0,0 +1,428 @@
+use crate::{
+    adapters::{
+        anilist_client::{ANILIST_PIN_REDIRECT, AniListClient, AniListToken},
+        kitsu_client::{KitsuClient, KitsuToken},
+        mal_client::{MalListEntry, MalSyncGateway, build_mal_client_if_enabled},
+        providers::{ports::AnimeProvider, registry},
+        remote_id_cache::RemoteIdCache,
+        sync_backend::{SyncService, build_sync_backends},
+        sync_queue::SyncQueue,
+    },
+    config::AppConfig,
+    history::{History, HistoryEntry},
+    types::{EpisodeCounts, Provider, ShowInfo, Translation, normalize_title},
+};
+
+use std::cmp::Reverse;
+
+use anyhow::{Context, Result, bail};
+
+/// `anv sync enable [--service <service>]` — authenticates with the service if needed.
+pub async fn run_sync_enable(config: &AppConfig, service: SyncService) -> Result<()> {
+    match service {
+        SyncService::Mal => run_sync_enable_mal(config).await,
+        SyncService::Anilist => run_sync_enable_anilist(config).await,
+        SyncService::Kitsu => run_sync_enable_kitsu(config).await,
+    }
+}
+
+/// Tells the user how to switch sync on for `service` once authenticated.
+fn print_activation_hint(config: &AppConfig, service: SyncService) {
+    if config.sync.enabled && config.sync.services.contains(&service) {
+        println!("Sync is active.");
+        return;
+    }
+    println!(
+        "\nTo activate sync, set `sync.enabled = true` and add \"{}\" to `sync.services` in:\n  {}",
+        service.as_str(),
+        config.path.display()
+    );
+}
+
+async fn run_sync_enable_mal(config: &AppConfig) -> Result<()> {
+    let sync_gateway = MalSyncGateway;
+    if config.sync.client_id.is_empty() {
+        bail!(
+            "MAL client_id is not set.\n\
+             1. Go to https://myanimelist.net/apiconfig and create an application.\n\
+             2. Set the app type to 'other' and redirect URI to: http://localhost:11422/callback\n\
+             3. Copy the Client ID and add it to your config:\n\
+             \n\
+             [sync]\n\
+             client_id = \"<your-client-id>\"\n\
+             \n\
+             Config location: {}",
+            config.path.display()
+        );
+    }
+
+    match sync_gateway.load_token()? {
+        Some(token) if !token.is_expired() => {
+            println!("Already authenticated with MyAnimeList.");
+            print_activation_hint(config, SyncService::Mal);
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let client_id = config.sync.client_id.clone();
+    let token = sync_gateway
+        .authenticate(&client_id)
+        .await
+        .context("MAL OAuth flow failed")?;
+
+    println!("\n✓ Successfully authenticated with MyAnimeList!");
+    println!(
+        "Token stored at: {}",
+        sync_gateway
+            .token_path()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|_| "<unknown>".into())
+    );
+    print_activation_hint(config, SyncService::Mal);
+    let _ = token;
+    Ok(())
+}
+
+async fn run_sync_enable_anilist(config: &AppConfig) -> Result<()> {
+    if config.sync.anilist_client_id.is_empty() {
+        bail!(
+            "AniList client ID is not set.\n\
+             1. Go to https://anilist.co/settings/developer and create a client.\n\
+             2. Set its redirect URL to: {ANILIST_PIN_REDIRECT}\n\
+             3. Copy the client ID and add it to your config:\n\
+             \n\
+             [sync]\n\
+             anilist_client_id = \"<your-client-id>\"\n\
+             \n\
+             Config location: {}",
+            config.path.display()
+        );
+    }
+
+    if let Some(token) = AniListToken::load()? {
+        if !token.is_expired() {
+            println!("Already authenticated with AniList.");
+            print_activation_hint(config, SyncService::Anilist);
+            return Ok(());
+        }
+    }
+
+    AniListClient::authenticate(&config.sync.anilist_client_id)
+        .await
+        .context("AniList authorization failed")?;
+
+    println!("\n✓ Successfully authenticated with AniList!");
+    println!(
+        "Token stored at: {}",
+        AniListToken::token_path()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|_| "<unknown>".into())
+    );
+    print_activation_hint(config, SyncService::Anilist);
+    Ok(())
+}
+
+async fn run_sync_enable_kitsu(config: &AppConfig) -> Result<()> {
+    if KitsuToken::load()?.is_some() {
+        println!("Already authenticated with Kitsu.");
+        print_activation_hint(config, SyncService::Kitsu);
+        return Ok(());
+    }
+
+    KitsuClient::authenticate()
+        .await
+        .context("Kitsu sign-in failed")?;
+
+    println!("\n✓ Successfully authenticated with Kitsu!");
+    println!(
+        "Token stored at: {}",
+        KitsuToken::token_path()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|_| "<unknown>".into())
+    );
+    print_activation_hint(config, SyncService::Kitsu);
+    Ok(())
+}
+
+/// `anv sync status` — show current sync/auth state.
+pub fn run_sync_status(config: &AppConfig) -> Result<()> {
+    let sync_gateway = MalSyncGateway;
+    println!("── Sync Status ──");
+    println!(
+        "  sync.enabled : {}",
+        if config.sync.enabled { "yes" } else { "no" }
+    );
+    let services: Vec<&str> = config
+        .sync
+        .services
+        .iter()
+        .map(|service| service.label())
+        .collect();
+    println!("  services     : {}", services.join(", "));
+
+    println!("── MAL ──");
+    print_client_id(config, &config.sync.client_id);
+    match sync_gateway.load_token() {
+        Ok(Some(token)) => {
+            if token.is_expired() {
+                println!("  token        : expired  (run `anv sync enable` to refresh)");
+            } else {
+                println!(
+                    "  token        : valid, expires {}",
+                    token.expires_at.format("%Y-%m-%d %H:%M UTC")
+                );
+            }
+        }
+        Ok(None) => println!("  token        : not found  (run `anv sync enable`)"),
+        Err(err) => println!("  token        : error reading ({err})"),
+    }
+
+    println!("── AniList ──");
+    print_client_id(config, &config.sync.anilist_client_id);
+    match AniListToken::load() {
+        Ok(Some(token)) if token.is_expired() => {
+            println!("  token        : expired  (run `anv sync enable --service anilist`)")
+        }
+        Ok(Some(token)) => println!(
+            "  token        : valid, expires {}",
+            token.expires_at.format("%Y-%m-%d %H:%M UTC")
+        ),
+        Ok(None) => {
+            println!("  token        : not found  (run `anv sync enable --service anilist`)")
+        }
+        Err(err) => println!("  token        : error reading ({err})"),
+    }
+
+    println!("── Kitsu ──");
+    match KitsuToken::load() {
+        // Expired Kitsu tokens are refreshed on the next launch.
+        Ok(Some(token)) => println!(
+            "  token        : stored, access token expires {}",
+            token.expires_at.format("%Y-%m-%d %H:%M UTC")
+        ),
+        Ok(None) => println!("  token        : not found  (run `anv sync enable --service kitsu`)"),
+        Err(err) => println!("  token        : error reading ({err})"),
+    }
+
+    println!("── Pending updates ──");
+    match SyncQueue::load() {
+        Ok(queue) if queue.entries.is_empty() => println!("  none"),
+        Ok(queue) => {
+            for entry in &queue.entries {
+                println!(
+                    "  {:<8} {}  (queued {})",
+                    entry.service.label(),
+                    entry.change.summary(),
+                    entry.queued_at.format("%Y-%m-%d %H:%M UTC")
+                );
+            }
+            println!("  run `anv sync flush` to send them now");
+        }
+        Err(err) => println!("  error reading queue ({err})"),
+    }
+    Ok(())
+}
+
+/// `anv sync flush` — send queued updates to the enabled services.
+pub async fn run_sync_flush(config: &AppConfig) -> Result<()> {
+    let mut queue = SyncQueue::load()?;
+    if queue.entries.is_empty() {
+        println!("No pending sync updates.");
+        return Ok(());
+    }
+    if !config.sync.enabled {
+        bail!(
+            "Sync is disabled; set `sync.enabled = true` in {} to send {} pending update(s).",
+            config.path.display(),
+            queue.entries.len()
+        );
+    }
+
+    let backends = build_sync_backends(config).await;
+    let report = queue.flush(&backends).await;
+    queue.save().context("failed to save sync queue")?;
+    println!(
+        "Sent {} update(s); {} failed and stay queued.",
+        report.sent, report.failed
+    );
+    if report.skipped > 0 {
+        println!(
+            "{} update(s) are for services not in `sync.services` and were left queued.",
+            report.skipped
+        );
+    }
+    Ok(())
+}
+
+fn print_client_id(config: &AppConfig, client_id: &str) {
+    if client_id.is_empty() {
+        println!(
+            "  client_id    : not set  (add to {})",
+            config.path.display()
+        );
+    } else {
+        let masked = format!("{}…", &client_id[..client_id.len().min(8)]);
+        println!("  client_id    : {}", masked);
+    }
+}
+
+/// `anv sync pull` — seed history from the MAL list (watching and plan to
+/// watch) so shows started elsewhere can be continued from `anv history`.
+pub async fn run_sync_pull(config: &AppConfig, history: &mut History) -> Result<()> {
+    let Some(mal) = build_mal_client_if_enabled(config).await else {
+        bail!("MAL is not connected. Set `sync.enabled = true` and run `anv sync enable` first.");
+    };
+    let mut ids = RemoteIdCache::load_for(SyncService::Mal).unwrap_or_else(|err| {
+        eprintln!("[sync] Warning: could not load ID cache ({err}), starting fresh.");
+        RemoteIdCache::default()
+    });
+    let allanime = registry::anime_provider(Provider::Allanime).anime_client(&config.streams)?;
+
+    let (mut imported, mut current) = (0, 0);
+    let mut unmatched = Vec::new();
+    for status in ["watching", "plan_to_watch"] {
+        let entries = mal
+            .fetch_anime_list(status)
+            .await
+            .with_context(|| format!("failed to fetch your MAL {status} list"))?;
+        for entry in entries {
+            let show = match ids.find_by_remote(Provider::Allanime, entry.mal_id) {
+                Some(show_id) => linked_show(allanime.as_ref(), history, show_id, &entry).await,
+                None => match find_show(allanime.as_ref(), &entry).await {
+                    Some(show) => {
+                        if let Err(err) =
+                            ids.insert_and_save(Provider::Allanime, &show.id, entry.mal_id)
+                        {
+                            eprintln!("[sync] Warning: could not save ID cache: {err}");
+                        }
+                        show
+                    }
+                    None => {
+                        unmatched.push(entry.title);
+                        continue;
+                    }
+                },
+            };
+            if seed_history(history, &show, &entry) {
+                imported += 1;
+            } else {
+                current += 1;
+            }
+        }
+    }
+
+    history
+        .entries
+        .sort_by_key(|entry| Reverse(entry.watched_at));
+    history.save()?;
+    println!("Imported {imported} show(s) from MAL; {current} already up to date in history.");
+    if !unmatched.is_empty() {
+        println!("No AllAnime match for {} show(s):", unmatched.len());
+        for title in &unmatched {
+            println!("  {title}");
+        }
+        println!("Play them once with `anv <name>` and sync on to link them.");
+    }
+    Ok(())
+}
+
+/// The already-linked AllAnime show `show_id`, titled the way AllAnime
+/// names it: from history when the show is there, otherwise from a search
+/// for the MAL titles. MAL's title is only a last resort.
+async fn linked_show(
+    client: &dyn AnimeProvider,
+    history: &History,
+    show_id: &str,
+    entry: &MalListEntry,
+) -> ShowInfo {
+    let mut title = history
+        .entries
+        .iter()
+        .find(|local| local.show_id == show_id && !local.is_manga)
+        .map(|local| local.show_title.clone());
+    for query in std::iter::once(&entry.title).chain(&entry.english_title) {
+        if title.is_some() {
+            break;
+        }
+        if let Ok(shows) = client.search_shows(query, Translation::Sub).await {
+            title = shows
+                .into_iter()
+                .find(|show| show.id == show_id)
+                .map(|show| show.title);
+        }
+    }
+    ShowInfo {
+        id: show_id.to_string(),
+        title: title.unwrap_or_else(|| entry.english_title.clone().unwrap_or(entry.title.clone())),
+        available_eps: EpisodeCounts::default(),
+    }
+}
+
+/// Searches AllAnime for the entry's romaji, then English, title and takes
+/// an exact (normalized) title match. Near matches are deliberately not
+/// guessed at; the caller lists those shows instead.
+async fn find_show(client: &dyn AnimeProvider, entry: &MalListEntry) -> Option<ShowInfo> {
+    for title in std::iter::once(&entry.title).chain(&entry.english_title) {
+        let wanted = normalize_title(title);
+        match client.search_shows(title, Translation::Sub).await {
+            Ok(shows) => {
+                if let Some(show) = shows
+                    .into_iter()
+                    .find(|show| normalize_title(&show.title) == wanted)
+                {
+                    return Some(show);
+                }
+            }
+            Err(err) => eprintln!("Search for \"{title}\" failed ({err})."),
+        }
+    }
+    None
+}
+
+/// Adds `entry` to `history` unless anv already has the show at the same or
+/// a later point. Returns whether it was added.
+fn seed_history(history: &mut History, show: &ShowInfo, entry: &MalListEntry) -> bool {
+    let local = history
+        .entries
+        .iter()
+        .find(|local| local.show_id == show.id && !local.is_manga);
+    if let Some(local) = local {
+        let local_episode = local.episode.parse::<f64>().ok();
+        if local.watched_at >= entry.updated_at
+            || local_episode.is_some_and(|ep| ep >= f64::from(entry.num_episodes_watched))
+        {
+            return false;
+        }
+    }
+    let translation = local.map_or(Translation::Sub, |local| local.translation);
+    history.upsert(HistoryEntry {
+        show_id: show.id.clone(),
+        show_title: show.title.clone(),
+        episode: entry.num_episodes_watched.to_string(),
+        translation,
+        provider: Provider::Allanime,
+        is_manga: false,
+        watched_at: entry.updated_at,
+        position: None,
+        duration: None,
+        in_progress: false,
+        imported_from: Some(SyncService::Mal),
+    });
+    true
+}
+
+/// `anv sync disable` — set sync.enabled = false and write config.
+pub async fn run_sync_disable(config: &mut AppConfig) -> Result<()> {
+    if !config.sync.enabled {
+        println!("Sync is already disabled.");
+        return Ok(());
+    }
+    config.sync.enabled = false;
+    config.save().context("failed to save config")?;
+    println!(
+        "Sync disabled. Edit {} to re-enable.",
+        config.path.display()
+    );
+    Ok(())
+}
@@ -1,19 +1,45 @@
+use crate::{
+    adapters::{player_backend::PlayerBackendKind, sync_backend::SyncService},
+    types::Provider,
+};
+
 use anyhow::{Context, Result, anyhow};
 use config::{Config, Environment, File, FileFormat};
 use dirs_next::config_dir;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::{fs, path::PathBuf};
 
 const FALLBACK_CONFIG_PATH: &str = "~/.config/anv/config.toml";
+/// AllAnime source names in order of preference, unless `[streams]
+/// source_priority` says otherwise. Every source is queried; this only
+/// decides how results are ordered.
+pub const DEFAULT_SOURCE_PRIORITY: &[&str] = &["Default", "S-mp4", "Luf-Mp4", "Yt-mp4"];
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AppConfig {
     #[serde(default = "default_player")]
     pub player: String,
 
+    /// How `player` is driven; `auto` guesses from the command.
+    #[serde(default)]
+    pub player_backend: PlayerBackendKind,
+
     #[serde(default)]
     pub binge: bool,
 
+    /// Percentage of an episode that must be played before it counts as watched.
+    #[serde(default = "default_watched_threshold")]
+    pub watched_threshold: u8,
+
+    #[serde(default)]
+    pub streams: StreamsConfig,
+
+    #[serde(default)]
+    pub subtitles: SubtitlesConfig,
+
+    #[serde(default)]
+    pub manga: MangaConfig,
+
     #[serde(default)]
     pub sync: SyncConfig,
 
@@ -21,41 +47,222 @@ pub struct AppConfig {
     pub path: PathBuf,
 }
 
-#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SyncConfig {
     #[serde(default)]
     pub enabled: bool,
 
+    /// List services progress is synced to. Older configs name a single
+    /// `service`, which is still accepted.
+    #[serde(
+        default = "default_sync_services",
+        alias = "service",
+        deserialize_with = "one_or_many_services"
+    )]
+    pub services: Vec<SyncService>,
+
     /// MAL API client ID from https://myanimelist.net/apiconfig
     #[serde(default)]
     pub client_id: String,
+
+    /// AniList API client ID from https://anilist.co/settings/developer
+    #[serde(default)]
+    pub anilist_client_id: String,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            services: default_sync_services(),
+            client_id: String::new(),
+            anilist_client_id: String::new(),
+        }
+    }
+}
+
+fn default_sync_services() -> Vec<SyncService> {
+    vec![SyncService::default()]
+}
+
+fn one_or_many_services<'de, D>(deserializer: D) -> Result<Vec<SyncService>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(SyncService),
+        Many(Vec<SyncService>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(service) => vec![service],
+        OneOrMany::Many(services) => services,
+    })
+}
+
+/// Policy used to pick a stream automatically when an episode has several.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StreamsConfig {
+    /// Preferred vertical resolution, e.g. 1080. Unset means best available.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferred_quality: Option<u32>,
+
+    /// Never auto-pick streams above this resolution (e.g. 720 on metered links).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_quality: Option<u32>,
+
+    #[serde(default)]
+    pub format: StreamFormat,
+
+    /// Source names in order of preference; unlisted sources rank last.
+    #[serde(default = "default_source_priority")]
+    pub source_priority: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamFormat {
+    #[default]
+    Any,
+    Hls,
+    Mp4,
+}
+
+impl Default for StreamsConfig {
+    fn default() -> Self {
+        Self {
+            preferred_quality: None,
+            max_quality: None,
+            format: StreamFormat::default(),
+            source_priority: default_source_priority(),
+        }
+    }
+}
+
+/// Which subtitle track is shown by default, and which are saved with downloads.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SubtitlesConfig {
+    /// Language codes or names, most preferred first; empty leaves the
+    /// default track to the player.
+    #[serde(default = "default_subtitle_languages")]
+    pub languages: Vec<String>,
+
+    /// Save the subtitle files next to downloaded episodes.
+    #[serde(default)]
+    pub save_with_downloads: bool,
+}
+
+impl Default for SubtitlesConfig {
+    fn default() -> Self {
+        Self {
+            languages: default_subtitle_languages(),
+            save_with_downloads: false,
+        }
+    }
+}
+
+fn default_subtitle_languages() -> Vec<String> {
+    vec!["en".to_string()]
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MangaConfig {
+    /// Providers tried in order when the selected one fails.
+    #[serde(default = "default_manga_providers")]
+    pub fallback_providers: Vec<Provider>,
+}
+
+impl Default for MangaConfig {
+    fn default() -> Self {
+        Self {
+            fallback_providers: default_manga_providers(),
+        }
+    }
+}
+
+fn default_manga_providers() -> Vec<Provider> {
+    vec![Provider::Allanime, Provider::Mangadex, Provider::Mangapill]
+}
+
+fn default_source_priority() -> Vec<String> {
+    DEFAULT_SOURCE_PRIORITY
+        .iter()
+        .map(|name| name.to_string())
+        .collect()
 }
 
 fn default_player() -> String {
     "mpv".to_string()
 }
 
+fn default_watched_threshold() -> u8 {
+    85
+}
+
 const CONFIG_HEADER: &str = "# anv configuration
 # Docs: https://github.com/Vedant-Asati03/anv
 #
 # player — media player command (default: \"mpv\")
 #           also overridable with ANV_PLAYER env var
 #
+# player_backend — \"auto\" (default), \"mpv\", \"vlc\" or \"template\"
+#           auto picks vlc for vlc/cvlc, template when player contains {url},
+#           and mpv otherwise. A template maps fields into argv, e.g.
+#           player = \"iina --no-stdin --mpv-force-media-title={title} {url}\"
+#           placeholders: {url} {title} {subtitle} {start} {referer}
+#           {user_agent} {header}; arguments with an empty placeholder are
+#           dropped. Resume and the shared binge player need mpv.
+#
 # binge   — set to true to auto-play the next episode without prompting
-#           (can also be enabled per-session with the --binge flag)
+#           (can also be enabled per-session with the --binge flag); with
+#           mpv, episodes are queued in one player window
+#
+# watched_threshold — percent of an episode you must play before it is
+#           marked watched and synced (default: 85); below it the episode
+#           stays \"in progress\" and resumes where you stopped
+#
+# [streams] — how a stream is picked when an episode has several
+#   preferred_quality — e.g. 1080; closest lower quality is used if missing
+#   max_quality       — never auto-pick above this resolution, e.g. 720
+#   format            — \"any\", \"hls\" or \"mp4\"
+#   source_priority   — source names, most preferred first
+#   (pass --pick-stream to choose manually instead)
+#
+# [subtitles]
+#   languages           — e.g. [\"es\", \"pt\", \"en\"]; every track is loaded, matching
+#                         ones first in this order, and the first match is shown
+#   save_with_downloads — also save the subtitle files next to downloads
+#
+# [manga]
+#   fallback_providers — providers to switch to, in order, when the selected
+#                        one fails or its image CDN is blocked
 #
 # [sync]
-#   enabled — set to true to sync watch status to MAL after each episode
+#   enabled — set to true to sync watch status after each episode and
+#             reading progress after each manga chapter (MAL only)
+#   services — list services to sync to, any of \"mal\" (default),
+#              \"anilist\" and \"kitsu\"; each watched episode goes to all of them
 #   client_id — your MAL API client ID
 #               register at https://myanimelist.net/apiconfig
 #               redirect URI must be: http://localhost:11422/callback
+#   anilist_client_id — your AniList API client ID
+#               register at https://anilist.co/settings/developer
+#               redirect URL must be: https://anilist.co/api/v2/oauth/pin
+#   (Kitsu needs no client ID; `anv sync enable --service kitsu` signs in)
 ";
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
             player: default_player(),
+            player_backend: PlayerBackendKind::default(),
             binge: false,
+            watched_threshold: default_watched_threshold(),
+            streams: StreamsConfig::default(),
+            subtitles: SubtitlesConfig::default(),
+            manga: MangaConfig::default(),
             sync: SyncConfig::default(),
             path: config_path(),
         }
@@ -4,7 +4,10 @@ use dirs_next::data_dir;
 use serde::{Deserialize, Serialize};
 use std::{fs, path::PathBuf};
 
-use crate::types::{Provider, Translation};
+use crate::{
+    adapters::sync_backend::SyncService,
+    types::{Provider, Translation},
+};
 
 const FALLBACK_HISTORY_PATH: &str = "~/.local/share/anv/history.json";
 
@@ -19,6 +22,29 @@ pub struct HistoryEntry {
     #[serde(default)]
     pub is_manga: bool,
     pub watched_at: DateTime<Utc>,
+    /// Seconds into the episode where playback stopped, if it was left unfinished.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<f64>,
+    /// Episode length in seconds, when the player reported it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+    /// Playback stopped before the watched threshold, so the episode does not
+    /// count as watched yet.
+    #[serde(default)]
+    pub in_progress: bool,
+    /// Set for entries seeded from a list service by `anv sync pull`: the show
+    /// was watched up to `episode` elsewhere, so the next one is offered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub imported_from: Option<SyncService>,
+}
+
+impl HistoryEntry {
+    /// The episode to continue an imported entry from, one past `episode`.
+    pub fn continue_episode(&self) -> Option<String> {
+        self.imported_from?;
+        let watched: u32 = self.episode.parse().ok()?;
+        Some((watched + 1).to_string())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -82,6 +108,20 @@ impl History {
             .map(|e| e.episode.clone())
     }
 
+    /// Where to resume `episode`, if it was left unfinished last time.
+    pub fn resume_position(
+        &self,
+        show_id: &str,
+        translation: Translation,
+        episode: &str,
+    ) -> Option<f64> {
+        self.entries
+            .iter()
+            .find(|e| e.show_id == show_id && e.translation == translation && !e.is_manga)
+            .filter(|e| e.episode == episode)
+            .and_then(|e| e.position)
+    }
+
     pub fn last_chapter(&self, show_id: &str, translation: Translation) -> Option<String> {
         self.entries
             .iter()
@@ -3,6 +3,7 @@ pub mod cli;
 pub mod commands;
 pub mod config;
 pub mod history;
+pub mod library;
 pub mod progression;
 pub mod prompt;
 pub mod types;
@@ -0,0 +1,364 @@
+use anyhow::{Context, Result, anyhow};
+use dirs_next::data_dir;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    adapters::{
+        downloader::{default_download_dir, sanitize_file_name, show_dir_name},
+        reader::cache::{CachedChapterInfo, manga_cache_root, sanitize_cache_segment},
+    },
+    history::History,
+    progression::sorted_labels_numeric,
+    types::{Provider, SubtitleTrack, Translation},
+};
+
+/// Written next to downloaded episodes and cached pages so the offline
+/// library knows which show or manga a directory belongs to.
+const INFO_FILE: &str = ".anv.json";
+/// Download directories besides the default one, recorded by `anv download -o`
+/// so the offline library can find them again.
+const DOWNLOAD_ROOTS_FILE: &str = "download_roots.json";
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "ts"];
+const SUBTITLE_EXTENSIONS: &[&str] = &["ass", "ssa", "srt", "vtt"];
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "png", "webp", "avif", "gif"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryInfo {
+    pub id: String,
+    pub title: String,
+    pub provider: Provider,
+    /// Set for anime downloads; manga keeps one subdirectory per translation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translation: Option<Translation>,
+}
+
+impl LibraryInfo {
+    fn read(dir: &Path) -> Option<Self> {
+        let text = fs::read_to_string(dir.join(INFO_FILE)).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    /// Records what `dir` holds; a no-op when it is already recorded.
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(INFO_FILE);
+        let json = serde_json::to_string_pretty(self)?;
+        if fs::read_to_string(&path).is_ok_and(|existing| existing == json) {
+            return Ok(());
+        }
+        fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create directory {}", dir.display()))?;
+        fs::write(&path, json).with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+/// Everything that can be watched or read without a network connection.
+#[derive(Debug, Default)]
+pub struct Library {
+    pub shows: Vec<LocalShow>,
+    pub mangas: Vec<LocalManga>,
+}
+
+#[derive(Debug)]
+pub struct LocalShow {
+    pub id: String,
+    pub title: String,
+    pub provider: Provider,
+    pub translation: Translation,
+    /// Sorted by episode number.
+    pub episodes: Vec<LocalEpisode>,
+}
+
+#[derive(Debug)]
+pub struct LocalEpisode {
+    pub label: String,
+    pub path: PathBuf,
+    pub subtitles: Vec<SubtitleTrack>,
+}
+
+#[derive(Debug)]
+pub struct LocalManga {
+    pub id: String,
+    pub title: String,
+    pub provider: Provider,
+    pub translation: Translation,
+    /// Sorted by chapter number.
+    pub chapters: Vec<LocalChapter>,
+}
+
+#[derive(Debug)]
+pub struct LocalChapter {
+    pub label: String,
+    pub pages: Vec<PathBuf>,
+    /// Page count the provider listed when the chapter was cached; `None`
+    /// for chapters cached before it was recorded.
+    pub expected_pages: Option<usize>,
+}
+
+impl Library {
+    /// Indexes the download directories and the manga page cache.
+    /// Directories written before `.anv.json` existed are matched against
+    /// `history`.
+    pub fn scan(history: &History, cache_base_override: Option<&Path>) -> Result<Self> {
+        let mut library = Library::default();
+        for root in download_roots() {
+            library.shows.extend(scan_shows(&root, history));
+        }
+        library.shows.sort_by_key(|s| s.title.to_lowercase());
+        library.mangas = scan_mangas(&manga_cache_root(cache_base_override)?, history);
+        Ok(library)
+    }
+
+    pub fn show(&self, id: &str, translation: Translation) -> Option<&LocalShow> {
+        self.shows
+            .iter()
+            .find(|s| s.id == id && s.translation == translation)
+    }
+
+    pub fn manga(&self, id: &str, translation: Translation) -> Option<&LocalManga> {
+        self.mangas
+            .iter()
+            .find(|m| m.id == id && m.translation == translation)
+    }
+}
+
+impl LocalChapter {
+    /// Whether every page the provider listed is on disk. Chapters without a
+    /// recorded page count cannot be checked and count as partial.
+    pub fn is_complete(&self) -> bool {
+        self.expected_pages
+            .is_some_and(|expected| self.pages.len() >= expected)
+    }
+}
+
+impl LocalShow {
+    pub fn labels(&self) -> Vec<String> {
+        self.episodes.iter().map(|e| e.label.clone()).collect()
+    }
+}
+
+impl LocalManga {
+    pub fn labels(&self) -> Vec<String> {
+        self.chapters.iter().map(|c| c.label.clone()).collect()
+    }
+}
+
+fn scan_shows(root: &Path, history: &History) -> Vec<LocalShow> {
+    let mut shows = Vec::new();
+    for dir in subdirectories(root) {
+        let dir_name = file_name(&dir);
+        let info = LibraryInfo::read(&dir).unwrap_or_else(|| {
+            let shows = || history.entries.iter().filter(|e| !e.is_manga);
+            let entry = shows()
+                .find(|e| show_dir_name(&e.show_title, e.translation) == dir_name)
+                .or_else(|| shows().find(|e| sanitize_file_name(&e.show_title) == dir_name));
+            LibraryInfo {
+                id: entry.map_or_else(|| format!("local:{dir_name}"), |e| e.show_id.clone()),
+                title: entry.map_or_else(|| dir_name.clone(), |e| e.show_title.clone()),
+                provider: entry.map(|e| e.provider).unwrap_or_default(),
+                translation: entry.map(|e| e.translation),
+            }
+        });
+
+        let prefix = format!("{} - Episode ", sanitize_file_name(&info.title));
+        let files = files_in(&dir);
+        let mut episodes: Vec<LocalEpisode> = files
+            .iter()
+            .filter(|path| has_extension(path, VIDEO_EXTENSIONS))
+            .filter_map(|path| {
+                let stem = path.file_stem()?.to_string_lossy().into_owned();
+                let label = stem.strip_prefix(&prefix)?.to_string();
+                Some(LocalEpisode {
+                    label,
+                    path: path.clone(),
+                    subtitles: subtitles_for(&stem, &files),
+                })
+            })
+            .collect();
+        if episodes.is_empty() {
+            continue;
+        }
+        let order =
+            sorted_labels_numeric(&episodes.iter().map(|e| e.label.clone()).collect::<Vec<_>>());
+        episodes.sort_by_key(|e| order.iter().position(|label| *label == e.label));
+        shows.push(LocalShow {
+            id: info.id,
+            title: info.title,
+            provider: info.provider,
+            translation: info.translation.unwrap_or(Translation::Sub),
+            episodes,
+        });
+    }
+    shows.sort_by_key(|s| s.title.to_lowercase());
+    shows
+}
+
+/// Subtitle files saved as `<episode stem>.<lang>.<ext>`.
+fn subtitles_for(stem: &str, files: &[PathBuf]) -> Vec<SubtitleTrack> {
+    let prefix = format!("{stem}.");
+    files
+        .iter()
+        .filter(|path| has_extension(path, SUBTITLE_EXTENSIONS))
+        .filter_map(|path| {
+            let name = file_name(path);
+            let rest = name.strip_prefix(&prefix)?;
+            let lang = rest.split('.').next().map(str::to_string);
+            Some(SubtitleTrack {
+                url: path.to_string_lossy().into_owned(),
+                lang: lang.clone(),
+                label: lang,
+                default: false,
+            })
+        })
+        .collect()
+}
+
+fn scan_mangas(root: &Path, history: &History) -> Vec<LocalManga> {
+    let mut mangas = Vec::new();
+    for dir in subdirectories(root) {
+        let dir_name = file_name(&dir);
+        let info = LibraryInfo::read(&dir).or_else(|| {
+            history
+                .entries
+                .iter()
+                .find(|e| e.is_manga && sanitize_cache_segment(&e.show_id) == dir_name)
+                .map(|e| LibraryInfo {
+                    id: e.show_id.clone(),
+                    title: e.show_title.clone(),
+                    provider: e.provider,
+                    translation: None,
+                })
+        });
+        // Without a title there is nothing sensible to show for the directory.
+        let Some(info) = info else {
+            continue;
+        };
+
+        for translation_dir in subdirectories(&dir) {
+            let name = file_name(&translation_dir);
+            let Some(translation) = [Translation::Sub, Translation::Raw, Translation::Dub]
+                .into_iter()
+                .find(|t| t.as_str() == name)
+            else {
+                continue;
+            };
+            let mut chapters: Vec<LocalChapter> = subdirectories(&translation_dir)
+                .into_iter()
+                .filter_map(|chapter_dir| {
+                    let mut pages: Vec<PathBuf> = files_in(&chapter_dir)
+                        .into_iter()
+                        .filter(|path| has_extension(path, IMAGE_EXTENSIONS))
+                        .collect();
+                    pages.sort();
+                    (!pages.is_empty()).then(|| LocalChapter {
+                        label: file_name(&chapter_dir),
+                        pages,
+                        expected_pages: CachedChapterInfo::read(&chapter_dir)
+                            .map(|info| info.page_count),
+                    })
+                })
+                .collect();
+            if chapters.is_empty() {
+                continue;
+            }
+            let order = sorted_labels_numeric(
+                &chapters.iter().map(|c| c.label.clone()).collect::<Vec<_>>(),
+            );
+            chapters.sort_by_key(|c| order.iter().position(|label| *label == c.label));
+            mangas.push(LocalManga {
+                id: info.id.clone(),
+                title: info.title.clone(),
+                provider: info.provider,
+                translation,
+                chapters,
+            });
+        }
+    }
+    mangas.sort_by_key(|m| m.title.to_lowercase());
+    mangas
+}
+
+fn download_roots_path() -> Result<PathBuf> {
+    let base = data_dir().ok_or_else(|| anyhow!("Could not determine data directory"))?;
+    Ok(base.join("anv").join(DOWNLOAD_ROOTS_FILE))
+}
+
+fn recorded_download_roots() -> Vec<PathBuf> {
+    download_roots_path()
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// The default download directory followed by every recorded one.
+fn download_roots() -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = default_download_dir().into_iter().collect();
+    for root in recorded_download_roots() {
+        if !roots.contains(&root) {
+            roots.push(root);
+        }
+    }
+    roots
+}
+
+/// Remembers `root` as a download directory so `--offline` indexes it. The
+/// default directory is always scanned and is not recorded.
+pub fn record_download_root(root: &Path) -> Result<()> {
+    let root =
+        fs::canonicalize(root).with_context(|| format!("failed to resolve {}", root.display()))?;
+    let is_default = default_download_dir()
+        .ok()
+        .and_then(|dir| fs::canonicalize(dir).ok())
+        .is_some_and(|dir| dir == root);
+    let mut roots = recorded_download_roots();
+    if is_default || roots.contains(&root) {
+        return Ok(());
+    }
+    roots.push(root);
+
+    let path = download_roots_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create data directory {}", parent.display()))?;
+    }
+    let json = serde_json::to_string_pretty(&roots)?;
+    fs::write(&path, json).with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn subdirectories(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect()
+}
+
+fn files_in(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.contains(&ext.to_ascii_lowercase().as_str()))
+}
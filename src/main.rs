@@ -1,11 +1,17 @@
 use anv::{
-    adapters::mal_client::build_mal_client_if_enabled,
+    adapters::{
+        providers::metadata_cache, sync_backend::build_sync_backends, sync_queue::replay_sync_queue,
+    },
     cli::{Cli, Commands, SyncAction},
     commands::{
+        download_anime::run_download_command,
+        export_manga::run_export_command,
         history::run_history_command,
-        play_anime::run_anime_command,
+        offline::run_offline_command,
+        play_anime::{PlaybackSettings, run_anime_command},
+        providers::run_providers_command,
         read_manga::run_manga_command,
-        sync_mal::{run_sync_disable, run_sync_enable_mal, run_sync_status},
+        sync::{run_sync_disable, run_sync_enable, run_sync_flush, run_sync_pull, run_sync_status},
     },
     config::AppConfig,
     history::History,
@@ -16,6 +22,7 @@ use clap::Parser;
 
 async fn run() -> Result<()> {
     let cli = Cli::parse();
+    metadata_cache::set_refresh(cli.refresh);
     let mut history = History::default().load().unwrap_or_else(|err| {
         eprintln!("Warning: failed to load history: {err}");
         History::default()
@@ -25,45 +32,87 @@ async fn run() -> Result<()> {
         AppConfig::default()
     });
 
+    let settings = PlaybackSettings::new(&cli, &config);
+
+    if cli.offline && matches!(cli.command, None | Some(Commands::History)) {
+        let history_mode = cli.command.is_some();
+        return run_offline_command(&cli, &mut history, history_mode, &settings).await;
+    }
+
+    // Queued list updates go out before playing, reading or resuming from
+    // history, ahead of the new ones those add. Offline mode never touches
+    // the network, and the other subcommands do not sync.
+    let sync = if !cli.offline && matches!(cli.command, None | Some(Commands::History)) {
+        let sync = build_sync_backends(&config).await;
+        replay_sync_queue(&sync).await;
+        sync
+    } else {
+        Vec::new()
+    };
+
     match cli.command {
         Some(Commands::History) => {
-            return run_history_command(
+            return run_history_command(&cli, &mut history, true, &settings, &config.manga, &[])
+                .await;
+        }
+        Some(Commands::Download {
+            ref episodes,
+            ref quality,
+            ref output,
+            ref query,
+        }) => {
+            return run_download_command(
+                &cli,
+                &config,
+                query,
+                episodes.as_deref(),
+                quality.as_deref(),
+                output.as_deref(),
+            )
+            .await;
+        }
+        Some(Commands::Export {
+            ref chapters,
+            ref format,
+            ref output,
+            ref query,
+        }) => {
+            return run_export_command(
                 &cli,
-                &mut history,
-                true,
-                &config.player,
-                None,
-                cli.binge || config.binge,
+                &config.manga,
+                query,
+                chapters.as_deref(),
+                format,
+                output.as_deref(),
             )
             .await;
         }
+        Some(Commands::Providers { ref name }) => {
+            return run_providers_command(name.as_deref());
+        }
         Some(Commands::Sync {
-            action: SyncAction::Enable,
-        }) => return run_sync_enable_mal(&config).await,
+            action: SyncAction::Enable { service },
+        }) => return run_sync_enable(&config, service).await,
         Some(Commands::Sync {
             action: SyncAction::Status,
         }) => return run_sync_status(&config),
+        Some(Commands::Sync {
+            action: SyncAction::Flush,
+        }) => return run_sync_flush(&config).await,
+        Some(Commands::Sync {
+            action: SyncAction::Pull,
+        }) => return run_sync_pull(&config, &mut history).await,
         Some(Commands::Sync {
             action: SyncAction::Disable,
         }) => return run_sync_disable(&mut config).await,
         None => {}
     }
 
-    let mal_client = build_mal_client_if_enabled(&config).await;
-
     if cli.manga {
-        return run_manga_command(&cli, &mut history).await;
+        return run_manga_command(&cli, &mut history, &config.manga, &settings.player, &sync).await;
     }
 
-    let binge = cli.binge || config.binge;
-    run_anime_command(
-        &cli,
-        &mut history,
-        &config.player,
-        mal_client.as_ref(),
-        binge,
-    )
-    .await
+    run_anime_command(&cli, &mut history, &settings, &sync).await
 }
 
 #[tokio::main]
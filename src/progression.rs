@@ -1,3 +1,6 @@
+use anyhow::{Result, anyhow, bail};
+use std::collections::HashSet;
+
 pub fn sorted_labels_numeric(labels: &[String]) -> Vec<String> {
     let mut sorted = labels.to_vec();
     sorted.sort_by(|a, b| {
@@ -17,3 +20,102 @@ pub fn next_label_presorted(current: &str, sorted: &[String]) -> Option<String>
 fn parse_numeric_label(label: &str) -> f64 {
     label.parse::<f64>().unwrap_or(0.0)
 }
+
+/// Resolves an episode/chapter range spec such as `"1-12"`, `"5"` or `"1-3,7"`
+/// against the available labels, returning matches in sorted order.
+///
+/// Range bounds are compared numerically, so `"1-2"` also picks up `"1.5"`.
+/// Single items must match a label exactly.
+pub fn labels_in_range(spec: &str, sorted: &[String]) -> Result<Vec<String>> {
+    let mut selected: Vec<String> = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let start = start
+                    .trim()
+                    .parse::<f64>()
+                    .map_err(|_| anyhow!("invalid range start in '{part}'"))?;
+                let end = end
+                    .trim()
+                    .parse::<f64>()
+                    .map_err(|_| anyhow!("invalid range end in '{part}'"))?;
+                if start > end {
+                    bail!("range '{part}' is reversed");
+                }
+                selected.extend(
+                    sorted
+                        .iter()
+                        .filter(|label| {
+                            label
+                                .parse::<f64>()
+                                .is_ok_and(|num| num >= start && num <= end)
+                        })
+                        .cloned(),
+                );
+            }
+            None => {
+                if !sorted.iter().any(|label| label == part) {
+                    bail!("'{part}' is not available");
+                }
+                selected.push(part.to_string());
+            }
+        }
+    }
+    // A label picked by several parts ("1-3,2") is kept once. `dedup` alone
+    // misses copies that the sort left apart, e.g. around "1.0" in "1,1.0,1".
+    let mut seen = HashSet::new();
+    let mut selected = sorted_labels_numeric(&selected);
+    selected.retain(|label| seen.insert(label.clone()));
+    if selected.is_empty() {
+        bail!("'{spec}' does not match any available label");
+    }
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn resolves_ranges_and_single_items() {
+        let available = labels(&["1", "2", "3", "3.5", "4", "5", "6", "7", "8"]);
+        assert_eq!(
+            labels_in_range("1-3,7", &available).unwrap(),
+            labels(&["1", "2", "3", "7"])
+        );
+        assert_eq!(
+            labels_in_range("3-4", &available).unwrap(),
+            labels(&["3", "3.5", "4"])
+        );
+        assert_eq!(
+            labels_in_range(" 8 , 2 ", &available).unwrap(),
+            labels(&["2", "8"])
+        );
+    }
+
+    #[test]
+    fn keeps_each_label_once() {
+        let available = labels(&["1", "1.0", "2", "3"]);
+        assert_eq!(
+            labels_in_range("1-3,2", &available).unwrap(),
+            labels(&["1", "1.0", "2", "3"])
+        );
+        assert_eq!(
+            labels_in_range("1,1.0,1", &available).unwrap(),
+            labels(&["1", "1.0"])
+        );
+    }
+
+    #[test]
+    fn rejects_bad_specs() {
+        let available = labels(&["1", "2"]);
+        assert!(labels_in_range("3-1", &available).is_err());
+        assert!(labels_in_range("x-2", &available).is_err());
+        assert!(labels_in_range("5", &available).is_err());
+        assert!(labels_in_range("10-12", &available).is_err());
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     history::{History, HistoryEntry},
-    types::{MangaInfo, ShowInfo, Translation},
+    types::{MangaInfo, MangaMatch, Provider, ShowInfo, Translation},
 };
 
 use anyhow::Result;
@@ -29,12 +29,28 @@ pub fn select_history_entry(history: &History) -> Result<Option<HistoryEntry>> {
             } else {
                 entry.translation.label()
             };
+            if let (Some(service), Some(next)) = (entry.imported_from, entry.continue_episode()) {
+                return format!(
+                    "[{}] Continue: {} ep {} · from {} {}",
+                    tag,
+                    entry.show_title,
+                    next,
+                    service.label(),
+                    entry.watched_at.format("%Y-%m-%d %H:%M")
+                );
+            }
+            let resume = match entry.position {
+                Some(pos) => format!(" (at {})", format_timestamp(pos)),
+                None if entry.in_progress => String::from(" (in progress)"),
+                None => String::new(),
+            };
             format!(
-                "[{}] {} · {} {} · watched {}",
+                "[{}] {} · {} {}{} · watched {}",
                 tag,
                 entry.show_title,
                 if entry.is_manga { "chapter" } else { "episode" },
                 entry.episode,
+                resume,
                 entry.watched_at.format("%Y-%m-%d %H:%M")
             )
         })
@@ -50,7 +66,7 @@ pub fn select_history_entry(history: &History) -> Result<Option<HistoryEntry>> {
 }
 
 pub fn select_show_entry(
-    shows: &Vec<ShowInfo>,
+    shows: &[ShowInfo],
     translation: Translation,
 ) -> Result<Option<&ShowInfo>> {
     if shows.is_empty() {
@@ -80,23 +96,46 @@ pub fn select_show_entry(
 }
 
 pub fn select_manga_entry(
-    mangas: &Vec<MangaInfo>,
+    matches: &[MangaMatch],
     translation: Translation,
-) -> Result<Option<&MangaInfo>> {
-    if mangas.is_empty() {
+) -> Result<Option<&MangaMatch>> {
+    if matches.is_empty() {
         println!("No mangas found.");
         return Ok(None);
     }
 
-    let items: Vec<String> = mangas
+    let first_provider = matches[0].sources.first().map(|(p, _)| *p);
+    let single_provider = matches
+        .iter()
+        .flat_map(|m| &m.sources)
+        .all(|(p, _)| Some(*p) == first_provider);
+
+    let items: Vec<String> = matches
         .iter()
         .map(|m| {
-            let count = match translation {
-                Translation::Sub => m.available_chapters.sub,
-                Translation::Raw => m.available_chapters.raw,
-                Translation::Dub => 0,
-            };
-            format!("{} [{} chapters]", m.title, count)
+            if single_provider {
+                // Only some providers report counts in search results.
+                let count = m
+                    .sources
+                    .first()
+                    .map_or(0, |(_, manga)| chapter_count(manga, translation));
+                if count == 0 {
+                    return m.title.clone();
+                }
+                return format!("{} [{} chapters]", m.title, count);
+            }
+            let counts: Vec<String> = m
+                .sources
+                .iter()
+                .map(|(provider, manga)| {
+                    format!(
+                        "{} {}",
+                        provider.as_str(),
+                        chapter_count(manga, translation)
+                    )
+                })
+                .collect();
+            format!("{} [{} chapters]", m.title, counts.join(" · "))
         })
         .collect();
 
@@ -106,7 +145,55 @@ pub fn select_manga_entry(
         .default(0)
         .interact_opt()?;
 
-    Ok(selection.map(|idx| &mangas[idx]))
+    Ok(selection.map(|idx| &matches[idx]))
+}
+
+/// Picks which provider to read a grouped title from; the one with the most
+/// chapters is preselected.
+pub fn select_manga_source(
+    found: &MangaMatch,
+    translation: Translation,
+) -> Result<Option<&(Provider, MangaInfo)>> {
+    if found.sources.len() <= 1 {
+        return Ok(found.sources.first());
+    }
+
+    let items: Vec<String> = found
+        .sources
+        .iter()
+        .map(|(provider, manga)| {
+            format!(
+                "{} · {} [{} chapters]",
+                provider.as_str(),
+                manga.title,
+                chapter_count(manga, translation)
+            )
+        })
+        .collect();
+    let most = found
+        .sources
+        .iter()
+        .enumerate()
+        .max_by_key(|(idx, (_, manga))| {
+            (chapter_count(manga, translation), std::cmp::Reverse(*idx))
+        })
+        .map_or(0, |(idx, _)| idx);
+
+    let selection = Select::with_theme(&theme())
+        .with_prompt("Read from which provider? (Esc to cancel)")
+        .items(&items)
+        .default(most)
+        .interact_opt()?;
+
+    Ok(selection.map(|idx| &found.sources[idx]))
+}
+
+fn chapter_count(manga: &MangaInfo, translation: Translation) -> usize {
+    match translation {
+        Translation::Sub => manga.available_chapters.sub,
+        Translation::Raw => manga.available_chapters.raw,
+        Translation::Dub => 0,
+    }
 }
 
 pub fn select_episode(
@@ -125,16 +212,17 @@ pub fn select_episode(
         .interact_opt()?)
 }
 
-pub fn rate(title: &str) -> Result<Option<u8>> {
+/// Asks for a 1–10 score to submit to `service`; `None` if skipped.
+pub fn rate(title: &str, service: &str) -> Result<Option<u8>> {
     let mut rating_options: Vec<String> = (1u8..=10).map(|n| format!("{}/10", n)).collect();
     rating_options.push("Skip (no rating)".to_string());
 
     let rating_idx = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt(format!("Rate \"{}\" on MAL (Esc to skip)", title))
+        .with_prompt(format!("Rate \"{}\" on {} (Esc to skip)", title, service))
         .items(&rating_options)
         .default(rating_options.len() - 1)
         .interact_opt()?;
-    Ok(rating_idx.map(|idx| rating_options[idx].parse::<u8>().unwrap_or(0)))
+    Ok(rating_idx.filter(|&idx| idx < 10).map(|idx| idx as u8 + 1))
 }
 
 pub fn confirm(prompt: &str) -> Result<bool> {
@@ -143,3 +231,14 @@ pub fn confirm(prompt: &str) -> Result<bool> {
         .default(false)
         .interact()?)
 }
+
+/// Formats seconds as `m:ss`, or `h:mm:ss` for anything an hour or longer.
+pub fn format_timestamp(seconds: f64) -> String {
+    let total = seconds.max(0.0) as u64;
+    let (h, m, s) = (total / 3600, (total % 3600) / 60, total % 60);
+    if h > 0 {
+        format!("{h}:{m:02}:{s:02}")
+    } else {
+        format!("{m}:{s:02}")
+    }
+}
@@ -1,34 +1,64 @@
-#[derive(Debug, Clone)]
+use serde::{Deserialize, Serialize};
+
+use super::Provider;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShowInfo {
     pub id: String,
     pub title: String,
     pub available_eps: EpisodeCounts,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct EpisodeCounts {
     pub sub: usize,
     pub dub: usize,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MangaInfo {
     pub id: String,
     pub title: String,
     pub available_chapters: ChapterCounts,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ChapterCounts {
     pub sub: usize,
     pub raw: usize,
 }
 
+/// One title as found on one or more providers, grouped by
+/// [`normalize_title`].
+#[derive(Debug, Clone)]
+pub struct MangaMatch {
+    pub title: String,
+    pub sources: Vec<(Provider, MangaInfo)>,
+}
+
 /// A manga chapter with a human-readable display label (e.g. `"271.5"`) and a
 /// provider-specific identifier used to fetch pages (may differ from the label,
 /// e.g. a UUID on MangaDex or a URL slug on Mangapill).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Chapter {
     pub id: String,
     pub label: String,
 }
+
+/// Lowercases `title` and drops punctuation so the same series can be matched
+/// across providers ("Kaguya-sama: Love Is War" == "kaguya sama love is war").
+pub fn normalize_title(title: &str) -> String {
+    title
+        .chars()
+        .map(|ch| {
+            if ch.is_alphanumeric() {
+                ch.to_lowercase().next().unwrap_or(ch)
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -3,7 +3,9 @@ pub mod provider;
 pub mod stream;
 pub mod translation;
 
-pub use media::{Chapter, ChapterCounts, EpisodeCounts, MangaInfo, ShowInfo};
+pub use media::{
+    Chapter, ChapterCounts, EpisodeCounts, MangaInfo, MangaMatch, ShowInfo, normalize_title,
+};
 pub use provider::Provider;
-pub use stream::{Page, StreamOption};
+pub use stream::{Page, StreamOption, SubtitleTrack};
 pub use translation::Translation;
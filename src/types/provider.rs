@@ -6,6 +6,18 @@ use serde::{Deserialize, Serialize};
 pub enum Provider {
     #[default]
     Allanime,
+    Animepahe,
     Mangadex,
     Mangapill,
 }
+
+impl Provider {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Provider::Allanime => "allanime",
+            Provider::Animepahe => "animepahe",
+            Provider::Mangadex => "mangadex",
+            Provider::Mangapill => "mangapill",
+        }
+    }
+}
@@ -8,7 +8,64 @@ pub struct StreamOption {
     pub quality_rank: i32,
     pub is_hls: bool,
     pub headers: HashMap<String, String>,
-    pub subtitle: Option<String>,
+    /// Every subtitle track the source offers, in source order.
+    pub subtitles: Vec<SubtitleTrack>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SubtitleTrack {
+    pub url: String,
+    /// Language code as reported by the source, e.g. "en" or "pt-BR".
+    pub lang: Option<String>,
+    /// Human-readable name, e.g. "Español".
+    pub label: Option<String>,
+    /// The track the player should show first.
+    pub default: bool,
+}
+
+impl SubtitleTrack {
+    /// Matches a configured language against the code ("pt" also matches
+    /// "pt-BR") or the label, ignoring case. Tracks without a code are
+    /// matched by label, so "en" still finds a track labelled "English".
+    pub fn matches_language(&self, wanted: &str) -> bool {
+        let wanted = wanted.trim();
+        let label_matches = || {
+            self.label.as_deref().is_some_and(|label| {
+                label.eq_ignore_ascii_case(wanted)
+                    || language_names(wanted)
+                        .iter()
+                        .any(|name| label.to_lowercase().starts_with(&name.to_lowercase()))
+            })
+        };
+        match self.lang.as_deref() {
+            Some(lang) => {
+                lang.eq_ignore_ascii_case(wanted)
+                    || lang
+                        .split(['-', '_'])
+                        .next()
+                        .is_some_and(|base| base.eq_ignore_ascii_case(wanted))
+                    || label_matches()
+            }
+            None => label_matches(),
+        }
+    }
+}
+
+/// English and native names sources use as labels for a language code.
+fn language_names(code: &str) -> &'static [&'static str] {
+    match code.to_ascii_lowercase().as_str() {
+        "en" => &["English"],
+        "es" => &["Spanish", "Español"],
+        "pt" => &["Portuguese", "Português"],
+        "fr" => &["French", "Français"],
+        "de" => &["German", "Deutsch"],
+        "it" => &["Italian", "Italiano"],
+        "ru" => &["Russian", "Русский"],
+        "ar" => &["Arabic", "العربية"],
+        "ja" => &["Japanese", "日本語"],
+        "id" => &["Indonesian", "Bahasa Indonesia"],
+        _ => &[],
+    }
 }
 
 impl StreamOption {